@@ -2,9 +2,11 @@ set -euxo pipefail
 
 main() {
     cargo test
+    cargo test --features embedded-dma
 
-    if [ $TRAVIS_RUST_VERSION = nightly]; then
+    if [ $TRAVIS_RUST_VERSION = nightly ]; then
         cargo test --features nightly
+        cargo test --features nightly,alloc
     fi
 }
 
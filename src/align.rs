@@ -0,0 +1,193 @@
+//! Over-aligned pool slots
+
+use core::{fmt, ops};
+
+/// 2-byte alignment marker
+#[derive(Clone, Copy)]
+#[repr(align(2))]
+pub struct A2;
+
+/// 4-byte alignment marker
+#[derive(Clone, Copy)]
+#[repr(align(4))]
+pub struct A4;
+
+/// 8-byte alignment marker
+#[derive(Clone, Copy)]
+#[repr(align(8))]
+pub struct A8;
+
+/// 16-byte alignment marker
+#[derive(Clone, Copy)]
+#[repr(align(16))]
+pub struct A16;
+
+/// 32-byte alignment marker
+#[derive(Clone, Copy)]
+#[repr(align(32))]
+pub struct A32;
+
+/// 64-byte alignment marker
+#[derive(Clone, Copy)]
+#[repr(align(64))]
+pub struct A64;
+
+/// A value of type `T` aligned to *at least* the alignment of `A`
+///
+/// Use `Aligned` as the element type of a memory pool to place every slot at an address that's a
+/// multiple of `A`'s alignment. The size of `Aligned<A, T>` is rounded up to a multiple of that
+/// alignment so no two slots will share e.g. a cache line when `A` is the size of a cache line.
+///
+/// The alignment is part of the type so it can be checked at compile time with
+/// `core::mem::align_of::<Aligned<A, T>>()`.
+///
+/// # Example
+///
+/// ```
+/// use alloc_singleton::{
+///     align::{Aligned, A32},
+///     stable::pool::{Box, Pool},
+/// };
+/// use owned_singleton::Singleton;
+///
+/// #[Singleton]
+/// static mut M: [Aligned<A32, [u8; 48]>; 4] = [
+///     Aligned::new([0; 48]),
+///     Aligned::new([0; 48]),
+///     Aligned::new([0; 48]),
+///     Aligned::new([0; 48]),
+/// ];
+///
+/// let mut pool = Pool::new(unsafe { M::new() });
+///
+/// let buffer: Box<M> = pool.alloc(Aligned::new([0; 48])).ok().unwrap();
+///
+/// // `Box` derefs to `Aligned` which, in turn, derefs to the buffer
+/// let bytes: &[u8; 48] = &buffer;
+/// assert_eq!(bytes.as_ptr() as usize % 32, 0);
+///
+/// pool.dealloc(buffer);
+/// ```
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Aligned<A, T> {
+    _alignment: [A; 0],
+    value: T,
+}
+
+impl<A, T> Aligned<A, T> {
+    /// Aligns the given `value`
+    pub const fn new(value: T) -> Self {
+        Aligned {
+            _alignment: [],
+            value,
+        }
+    }
+
+    /// Returns the inner value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<A, T> ops::Deref for Aligned<A, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<A, T> ops::DerefMut for Aligned<A, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(feature = "embedded-dma")]
+unsafe impl<A, T> embedded_dma::ReadTarget for Aligned<A, T>
+where
+    T: embedded_dma::ReadTarget,
+{
+    type Word = T::Word;
+
+    // NOTE the default implementation would include the trailing padding
+    fn as_read_buffer(&self) -> (*const T::Word, usize) {
+        self.value.as_read_buffer()
+    }
+}
+
+#[cfg(feature = "embedded-dma")]
+unsafe impl<A, T> embedded_dma::WriteTarget for Aligned<A, T>
+where
+    T: embedded_dma::WriteTarget,
+{
+    type Word = T::Word;
+
+    fn as_write_buffer(&mut self) -> (*mut T::Word, usize) {
+        self.value.as_write_buffer()
+    }
+}
+
+impl<A, T> fmt::Debug for Aligned<A, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt(&self.value, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem;
+
+    use owned_singleton::Singleton;
+
+    use super::{Aligned, A16, A2, A32, A4, A64, A8};
+    use crate::stable::pool::Pool;
+
+    #[test]
+    fn layout() {
+        assert_eq!(mem::align_of::<Aligned<A2, u8>>(), 2);
+        assert_eq!(mem::align_of::<Aligned<A4, u8>>(), 4);
+        assert_eq!(mem::align_of::<Aligned<A8, u8>>(), 8);
+        assert_eq!(mem::align_of::<Aligned<A16, u8>>(), 16);
+        assert_eq!(mem::align_of::<Aligned<A32, u8>>(), 32);
+        assert_eq!(mem::align_of::<Aligned<A64, u8>>(), 64);
+
+        // the alignment of `T` wins if it's bigger
+        assert_eq!(mem::align_of::<Aligned<A2, u64>>(), mem::align_of::<u64>());
+
+        assert_eq!(mem::size_of::<Aligned<A32, [u8; 33]>>(), 64);
+        assert_eq!(mem::size_of::<Aligned<A64, u8>>(), 64);
+    }
+
+    #[test]
+    fn slots() {
+        #[Singleton]
+        static mut M: [Aligned<A32, [u8; 40]>; 4] = [
+            Aligned::new([0; 40]),
+            Aligned::new([0; 40]),
+            Aligned::new([0; 40]),
+            Aligned::new([0; 40]),
+        ];
+
+        let mut pool = Pool::new(unsafe { M::new() });
+
+        let mut xs = vec![];
+        for i in 0..4 {
+            let x = pool.alloc(Aligned::new([i; 40])).unwrap();
+            let bytes: &[u8; 40] = &x;
+            assert_eq!(bytes.as_ptr() as usize % 32, 0);
+            assert_eq!(bytes[39], i);
+            xs.push(x);
+        }
+
+        // slots don't share 32-byte lines
+        for pair in xs.windows(2) {
+            let (a, b) = (&*pair[0] as *const _ as usize, &*pair[1] as *const _ as usize);
+            assert_eq!(b - a, 64);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Allocation errors
+
+use core::fmt;
+
+/// The reason an allocation failed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Cause {
+    /// The memory pool has no free slot left
+    Exhausted,
+    /// The allocation would exceed the quota given to the allocator
+    QuotaExceeded,
+    /// All the slots that were reserved up front have already been used
+    ReservationMissing,
+    /// The value is bigger, or more aligned, than the slots of the allocator
+    LayoutMismatch,
+}
+
+/// A failed allocation
+///
+/// `AllocError` gives back the value that couldn't be allocated (see `into_inner`) along with the
+/// cause of the failure and, when the allocator knows them, the number of values that were live
+/// and the capacity of the allocator at the time of the failure.
+///
+/// *NOTE*: The `Debug` implementation doesn't print the value so it doesn't require `T: Debug`.
+pub struct AllocError<T> {
+    cause: Cause,
+    usage: Option<(usize, usize)>,
+    value: T,
+}
+
+impl<T> AllocError<T> {
+    pub(crate) fn new(value: T, cause: Cause) -> Self {
+        AllocError {
+            cause,
+            usage: None,
+            value,
+        }
+    }
+
+    pub(crate) fn exhausted(value: T, live: usize, capacity: usize) -> Self {
+        AllocError::new(value, Cause::Exhausted).with_usage(live, capacity)
+    }
+
+    pub(crate) fn with_usage(self, live: usize, capacity: usize) -> Self {
+        AllocError {
+            usage: Some((live, capacity)),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    pub(crate) fn map<U, F>(self, f: F) -> AllocError<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        AllocError {
+            cause: self.cause,
+            usage: self.usage,
+            value: f(self.value),
+        }
+    }
+
+    /// Returns the cause of the failure
+    pub fn cause(&self) -> Cause {
+        self.cause
+    }
+
+    /// Returns the number of values that were live when the allocation failed, if known
+    pub fn live(&self) -> Option<usize> {
+        self.usage.map(|(live, _)| live)
+    }
+
+    /// Returns the capacity of the allocator, if known
+    pub fn capacity(&self) -> Option<usize> {
+        self.usage.map(|(_, capacity)| capacity)
+    }
+
+    /// Returns the value that couldn't be allocated
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> fmt::Debug for AllocError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AllocError")
+            .field("cause", &self.cause)
+            .field("live", &self.live())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for AllocError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self.cause {
+            Cause::Exhausted => "memory pool exhausted",
+            Cause::QuotaExceeded => "allocation quota exceeded",
+            Cause::ReservationMissing => "no reserved slot left",
+            Cause::LayoutMismatch => "value doesn't fit in a slot",
+        })?;
+
+        if let Some((live, capacity)) = self.usage {
+            write!(f, " ({}/{} in use)", live, capacity)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AllocError, Cause};
+
+    #[test]
+    fn fmt() {
+        // `Debug` doesn't need `T: Debug`
+        struct A;
+
+        let e = AllocError::exhausted(A, 4, 4);
+        assert_eq!(e.cause(), Cause::Exhausted);
+        assert_eq!(e.to_string(), "memory pool exhausted (4/4 in use)");
+        assert_eq!(
+            format!("{:?}", e),
+            "AllocError { cause: Exhausted, live: Some(4), capacity: Some(4) }"
+        );
+
+        let e = AllocError::new(42, Cause::LayoutMismatch);
+        assert_eq!(e.to_string(), "value doesn't fit in a slot");
+        assert_eq!(e.live(), None);
+        assert_eq!(e.into_inner(), 42);
+    }
+}
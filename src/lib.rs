@@ -6,14 +6,37 @@
 
 #![cfg_attr(feature = "nightly", feature(const_fn))]
 #![cfg_attr(feature = "nightly", feature(maybe_uninit))]
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(feature = "nightly", feature(ptr_metadata))]
+#![cfg_attr(feature = "nightly", feature(unsize))]
+#![cfg_attr(not(any(test, loom, feature = "std")), no_std)]
 #![deny(missing_docs)]
 #![deny(warnings)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
 extern crate as_slice;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "embedded-dma")]
+extern crate embedded_dma;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(loom)]
+extern crate loom;
 extern crate owned_singleton;
+#[cfg(feature = "serde")]
+extern crate serde;
 extern crate stable_deref_trait;
 
+#[cfg(feature = "nightly")]
+#[macro_use]
+mod sync;
+
+pub mod align;
+pub mod error;
 #[cfg(feature = "nightly")]
 pub mod nightly;
+pub mod observer;
+mod occupancy;
 pub mod stable;
+pub mod stats;
@@ -1,4 +1,5 @@
 //! Memory allocators that only work on nightly
 
 pub mod consts;
+pub mod executor;
 pub mod pool;
@@ -0,0 +1,424 @@
+//! Heap-less executor that runs futures stored in the slots of a memory pool
+
+use core::{
+    cell::Cell,
+    future::Future,
+    marker::{PhantomData, Unsize},
+    mem::{self, MaybeUninit},
+    ops,
+    pin::Pin,
+    ptr::{self, NonNull},
+    task::{Context, RawWaker, RawWakerVTable, Waker},
+};
+
+use generic_array::{
+    typenum::{consts::U256, IsLess, True},
+    ArrayLength, GenericArray,
+};
+use owned_singleton::Singleton;
+
+use crate::{
+    error::{AllocError, Cause},
+    nightly::pool::Pool,
+    sync::{AtomicU32, Ordering, UnsafeCell},
+};
+
+type Task = NonNull<dyn Future<Output = ()>>;
+
+/// An executor that can run up to `N` tasks concurrently
+///
+/// Each task (future) is stored in a slot of an internal memory pool whose element type is `T`;
+/// any future whose size and alignment are not greater than the size and alignment of `T` can be
+/// spawned. Use a byte array wrapped in `align::Aligned` to pick the slot layout. The slot of a
+/// task is recycled when the task completes.
+///
+/// Tasks are woken by marking their slot index in a "ready" bitmap so `Waker`s don't allocate and
+/// can be used from any context, e.g. interrupt handlers.
+///
+/// # Example
+///
+/// ```
+/// use core::{
+///     future::Future,
+///     pin::Pin,
+///     task::{Context, Poll},
+/// };
+///
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::{
+///     align::{Aligned, A8},
+///     nightly::{consts::*, executor::Executor},
+/// };
+///
+/// // a future that's not ready the first time it's polled
+/// struct YieldNow(bool);
+///
+/// impl Future for YieldNow {
+///     type Output = ();
+///
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+///         if self.0 {
+///             Poll::Ready(())
+///         } else {
+///             self.0 = true;
+///             cx.waker().wake_by_ref();
+///             Poll::Pending
+///         }
+///     }
+/// }
+///
+/// #[Singleton]
+/// static E: Executor<Aligned<A8, [u8; 32]>, U4> = Executor::new();
+///
+/// let executor = unsafe { E::new() };
+///
+/// executor.spawn(async {
+///     YieldNow(false).await;
+///     println!("Hello");
+/// }).ok().unwrap();
+///
+/// executor.spawn(async {
+///     println!("world!");
+/// }).ok().unwrap();
+///
+/// // runs until all the tasks have completed
+/// Executor::run(&executor);
+/// ```
+pub struct Executor<T, N>
+where
+    N: ArrayLength<T> + ArrayLength<MaybeUninit<Task>>,
+{
+    _not_send_or_sync: PhantomData<*const ()>,
+    // one bit per slot; set: holds a task
+    live: [Cell<u32>; 8],
+    pool: UnsafeCell<Pool<T, N>>,
+    // one bit per slot; set: the task needs to be polled
+    ready: [AtomicU32; 8],
+    tasks: UnsafeCell<MaybeUninit<GenericArray<MaybeUninit<Task>, N>>>,
+}
+
+impl<T, N> Executor<T, N>
+where
+    N: ArrayLength<T> + ArrayLength<MaybeUninit<Task>> + IsLess<U256, Output = True>,
+{
+    loom_const_fn! {
+        /// Creates a new executor
+        pub fn new() -> Self {
+            Executor {
+                _not_send_or_sync: PhantomData,
+                live: [
+                    Cell::new(0),
+                    Cell::new(0),
+                    Cell::new(0),
+                    Cell::new(0),
+                    Cell::new(0),
+                    Cell::new(0),
+                    Cell::new(0),
+                    Cell::new(0),
+                ],
+                pool: UnsafeCell::new(Pool::new()),
+                ready: [
+                    AtomicU32::new(0),
+                    AtomicU32::new(0),
+                    AtomicU32::new(0),
+                    AtomicU32::new(0),
+                    AtomicU32::new(0),
+                    AtomicU32::new(0),
+                    AtomicU32::new(0),
+                    AtomicU32::new(0),
+                ],
+                tasks: UnsafeCell::new(MaybeUninit::uninitialized()),
+            }
+        }
+    }
+}
+
+impl<T, N> Executor<T, N>
+where
+    N: ArrayLength<T> + ArrayLength<MaybeUninit<Task>>,
+{
+    /// Spawns the given `future` as a new task
+    ///
+    /// The task will be polled for the first time on the next `Executor::poll` call
+    ///
+    /// # Errors
+    ///
+    /// If the `future` doesn't fit in a slot, or `N` tasks are already running, an error containing
+    /// `future` is returned
+    pub fn spawn<F>(&self, future: F) -> Result<(), AllocError<F>>
+    where
+        F: Future<Output = ()> + Unsize<dyn Future<Output = ()>> + 'static,
+    {
+        assert!(mem::size_of::<T>() > 0);
+
+        if mem::size_of::<F>() > mem::size_of::<T>() || mem::align_of::<F>() > mem::align_of::<T>()
+        {
+            return Err(AllocError::new(future, Cause::LayoutMismatch));
+        }
+
+        unsafe {
+            if let Some(index) = self.pool.with_mut(|pool| (*pool).pop()) {
+                let task = self.pool.with_mut(|pool| {
+                    let slot = (*pool).slot(index) as *mut F;
+                    ptr::write(slot, future);
+
+                    NonNull::new_unchecked(slot as *mut dyn Future<Output = ()>)
+                });
+                self.set_task(index, task);
+
+                let (word, bit) = position(index);
+                self.live[word].set(self.live[word].get() | bit);
+                self.ready[word].fetch_or(bit, Ordering::Release);
+
+                Ok(())
+            } else {
+                Err(AllocError::exhausted(future, N::USIZE, N::USIZE))
+            }
+        }
+    }
+
+    /// Returns the number of tasks that have not yet completed
+    pub fn len(&self) -> usize {
+        self.live
+            .iter()
+            .map(|word| word.get().count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns `true` if all the tasks have completed
+    pub fn is_empty(&self) -> bool {
+        self.live.iter().all(|word| word.get() == 0)
+    }
+
+    /// Polls the tasks that have been woken up since the last call
+    ///
+    /// Returns `true` if some task was polled
+    pub fn poll<E>(executor: &E) -> bool
+    where
+        E: Singleton<Type = Self> + ops::Deref<Target = Self>,
+    {
+        let mut polled = false;
+
+        for word in 0..8 {
+            let mut ready = executor.ready[word].swap(0, Ordering::Acquire);
+
+            while ready != 0 {
+                let bit = ready.trailing_zeros();
+                ready &= !(1 << bit);
+
+                let index = (word * 32) as u8 + bit as u8;
+                // a task that has completed may still be woken up
+                if executor.live[word].get() & (1 << bit) == 0 {
+                    continue;
+                }
+
+                unsafe { executor.poll_task::<E>(index) }
+                polled = true;
+            }
+        }
+
+        polled
+    }
+
+    /// Polls the tasks until all of them have completed
+    ///
+    /// *NOTE*: This busy waits when no task is ready; use `Executor::poll` to put the processor to
+    /// sleep (e.g. `WFI`) in between polls
+    pub fn run<E>(executor: &E)
+    where
+        E: Singleton<Type = Self> + ops::Deref<Target = Self>,
+    {
+        while !executor.is_empty() {
+            Executor::poll(executor);
+        }
+    }
+
+    unsafe fn poll_task<E>(&self, index: u8)
+    where
+        E: Singleton<Type = Self>,
+    {
+        let mut task = self.task(index);
+
+        let waker = Waker::from_raw(RawWaker::new(
+            usize::from(index) as *const (),
+            &VTable::<E>::VTABLE,
+        ));
+        let mut cx = Context::from_waker(&waker);
+
+        // NOTE(unsafe) the future stays in its slot until it's dropped in place below
+        if Pin::new_unchecked(task.as_mut()).poll(&mut cx).is_ready() {
+            ptr::drop_in_place(task.as_ptr());
+            self.pool.with_mut(|pool| (*pool).push(index));
+
+            let (word, bit) = position(index);
+            self.live[word].set(self.live[word].get() & !bit);
+        }
+    }
+
+    // Returns the task stored in the slot `index`, which must hold a task
+    unsafe fn task(&self, index: u8) -> Task {
+        self.tasks.with(|tasks| {
+            *(*((*tasks).as_ptr() as *const MaybeUninit<Task>).add(usize::from(index))).as_ptr()
+        })
+    }
+
+    unsafe fn set_task(&self, index: u8, task: Task) {
+        self.tasks.with_mut(|tasks| {
+            ptr::write(
+                ((*tasks).as_mut_ptr() as *mut MaybeUninit<Task>).add(usize::from(index)),
+                MaybeUninit::new(task),
+            )
+        })
+    }
+}
+
+fn position(index: u8) -> (usize, u32) {
+    (usize::from(index / 32), 1 << (index % 32))
+}
+
+struct VTable<E>(PhantomData<E>);
+
+impl<T, N, E> VTable<E>
+where
+    E: Singleton<Type = Executor<T, N>>,
+    N: ArrayLength<T> + ArrayLength<MaybeUninit<Task>>,
+{
+    const VTABLE: RawWakerVTable =
+        RawWakerVTable::new(clone::<T, N, E>, wake::<T, N, E>, wake::<T, N, E>, drop);
+}
+
+// NOTE the data of the `RawWaker` is the slot index of the task
+unsafe fn clone<T, N, E>(data: *const ()) -> RawWaker
+where
+    E: Singleton<Type = Executor<T, N>>,
+    N: ArrayLength<T> + ArrayLength<MaybeUninit<Task>>,
+{
+    RawWaker::new(data, &VTable::<E>::VTABLE)
+}
+
+unsafe fn wake<T, N, E>(data: *const ())
+where
+    E: Singleton<Type = Executor<T, N>>,
+    N: ArrayLength<T> + ArrayLength<MaybeUninit<Task>>,
+{
+    let (word, bit) = position(data as usize as u8);
+
+    // NOTE only the atomic `ready` bitmap is accessed so this is fine to do from any context
+    (*E::get()).ready[word].fetch_or(bit, Ordering::Release);
+}
+
+unsafe fn drop(_: *const ()) {}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use core::{
+        future::Future,
+        pin::Pin,
+        sync::atomic::{AtomicUsize, Ordering},
+        task::{Context, Poll, Waker},
+    };
+    use std::{cell::RefCell, thread};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::Executor;
+    use crate::align::{Aligned, A8};
+
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn sanity() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        #[Singleton]
+        static E: Executor<Aligned<A8, [u8; 32]>, U2> = Executor::new();
+
+        let ref executor = unsafe { E::new() };
+
+        executor
+            .spawn(async {
+                YieldNow(false).await;
+                COUNT.fetch_add(1, Ordering::SeqCst);
+            })
+            .ok()
+            .unwrap();
+        executor
+            .spawn(async {
+                COUNT.fetch_add(10, Ordering::SeqCst);
+            })
+            .ok()
+            .unwrap();
+        assert!(executor.spawn(async {}).is_err());
+
+        // too big
+        let buffer = [0u8; 33];
+        assert!(executor.spawn(async move { assert_eq!(buffer[0], 0) }).is_err());
+
+        assert!(Executor::poll(executor));
+        assert_eq!(COUNT.load(Ordering::SeqCst), 10);
+        assert_eq!(executor.len(), 1);
+
+        // the slot of the completed task is recycled
+        executor.spawn(async {}).ok().unwrap();
+
+        Executor::run(executor);
+        assert_eq!(COUNT.load(Ordering::SeqCst), 11);
+        assert!(executor.is_empty());
+        assert!(!Executor::poll(executor));
+    }
+
+    #[test]
+    fn remote_wake() {
+        thread_local! {
+            static WAKER: RefCell<Option<Waker>> = RefCell::new(None);
+        }
+
+        struct Pending(bool);
+
+        impl Future for Pending {
+            type Output = ();
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+                if self.0 {
+                    Poll::Ready(())
+                } else {
+                    self.0 = true;
+                    WAKER.with(|w| *w.borrow_mut() = Some(cx.waker().clone()));
+                    Poll::Pending
+                }
+            }
+        }
+
+        #[Singleton]
+        static E: Executor<Aligned<A8, [u8; 16]>, U1> = Executor::new();
+
+        let ref executor = unsafe { E::new() };
+
+        executor.spawn(Pending(false)).ok().unwrap();
+
+        assert!(Executor::poll(executor));
+        assert!(!Executor::poll(executor));
+
+        // wake the task from another thread
+        let waker = WAKER.with(|w| w.borrow_mut().take()).unwrap();
+        thread::spawn(move || waker.wake()).join().unwrap();
+
+        assert!(Executor::poll(executor));
+        assert!(executor.is_empty());
+    }
+}
@@ -1,11 +1,35 @@
 //! Fixed size memory pool
 
+#[macro_use]
+mod macros;
+
+pub mod chain;
+pub mod deque;
+pub mod dyn_box;
+pub mod hash_map;
+pub mod list;
+pub mod multicore;
+pub mod ordered_map;
+pub mod pairing_heap;
+pub mod recycle;
+#[cfg(feature = "std")]
+pub mod shared;
+pub mod slot_map;
+pub mod string;
 pub mod unsend;
+pub mod vec;
+
+pub use self::{
+    deque::Deque, hash_map::HashMap, list::List, ordered_map::OrderedMap,
+    pairing_heap::PairingHeap, slot_map::SlotMap, string::String, vec::Vec,
+};
 
 use core::{
     marker::PhantomData,
     mem::{self, MaybeUninit},
-    ops, ptr,
+    ops,
+    pin::Pin,
+    ptr,
 };
 
 use generic_array::{
@@ -13,7 +37,16 @@ use generic_array::{
     ArrayLength, GenericArray,
 };
 use owned_singleton::Singleton;
-use stable_deref_trait::StableDeref;
+
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+    occupancy::Occupancy,
+    stats::PoolStats,
+};
+
+// "no slot" sentinel used by the pool based collections; a pool holds at most 255 elements
+const NIL: u8 = 255;
 
 /// A value allocated on the memory pool `P`
 ///
@@ -21,6 +54,8 @@ use stable_deref_trait::StableDeref;
 /// - `sizeof(Box<_>)` is a single byte
 /// - `Box<P>` implements `Send` if it derefs to a type `T` that implements `Send`
 /// - `Box<P>` implements `Sync` if it derefs to a type `T` that implements `Sync`
+/// - `Box<P>` can be handed to DMA APIs (`embedded_dma::{ReadBuffer, WriteBuffer}`) if it derefs to
+///   a word buffer like `[u16; N]` or `Aligned<A, [u8; N]>` (requires the `embedded-dma` feature)
 pub struct Box<P>
 where
     P: Singleton,
@@ -30,37 +65,99 @@ where
     index: u8,
 }
 
-impl<T, N, P> Box<P>
+impl<T, N, O, P> Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+{
+    /// Pins the value in place
+    ///
+    /// This is free: pool slots never move and the value is dropped in place when the `Box` is
+    /// freed
+    pub fn into_pin(b: Self) -> Pin<Self> {
+        unsafe { Pin::new_unchecked(b) }
+    }
+}
+
+impl<T, N, O, P> Box<P>
 where
-    P: Singleton<Type = Pool<T, N>> + ops::DerefMut<Target = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>> + ops::DerefMut<Target = Pool<T, N, O>>,
     N: ArrayLength<T>,
+    O: PoolObserver,
 {
     /// Allocates the given `value` on the pool
     ///
     /// # Errors
     ///
     /// If the memory pool has been exhausted an error containing `value` is returned
-    pub fn new(pool: &mut P, value: T) -> Result<Box<P>, T> {
+    pub fn new(pool: &mut P, value: T) -> Result<Box<P>, AllocError<T>> {
         unsafe {
             assert!(mem::size_of::<T>() > 0);
 
-            if pool.initialized < N::U8 {
-                let index = pool.initialized;
+            if let Some(index) = pool.pop() {
+                ptr::write(pool.slot(index), value);
 
-                let p = (pool.memory.as_mut_ptr() as *mut T).add(usize::from(index));
-
-                *(p as *mut u8) = index + 1;
-                pool.initialized += 1;
+                Ok(Box {
+                    _not_send_or_sync: PhantomData,
+                    _pool: PhantomData,
+                    index,
+                })
+            } else {
+                Err(AllocError::exhausted(value, N::USIZE, N::USIZE))
             }
+        }
+    }
 
-            if pool.free != 0 {
-                let index = pool.head;
-                let p = (pool.memory.as_mut_ptr() as *mut T).add(usize::from(index));
-                pool.head = *(p as *const u8);
+    /// Allocates the given `value` on the pool and pins it in place
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn pin(pool: &mut P, value: T) -> Result<Pin<Box<P>>, AllocError<T>> {
+        Box::new(pool, value).map(Box::into_pin)
+    }
 
-                pool.free -= 1;
+    /// Returns a `serde` seed that deserializes a value straight into the `pool`
+    ///
+    /// Deserialization fails if the pool has been exhausted
+    #[cfg(feature = "serde")]
+    pub fn seed(pool: &mut P) -> BoxSeed<'_, P> {
+        BoxSeed { pool }
+    }
 
-                ptr::write(p, value);
+    /// Returns this `Box` to the `pool`
+    ///
+    /// *NOTE*: This method must be invoked as `Box::free(x, pool)`, `x.free(pool)` doesn't compile.
+    pub fn free(self, pool: &mut P) {
+        unsafe {
+            ptr::drop_in_place(pool.slot(self.index));
+
+            pool.push(self.index);
+        }
+    }
+
+    /// Returns a pinned `Box` to the `pool`
+    ///
+    /// The value is dropped in place
+    pub fn free_pinned(b: Pin<Box<P>>, pool: &mut P) {
+        Box::free(unsafe { Pin::into_inner_unchecked(b) }, pool)
+    }
+
+    /// Moves the value into the pool `dst` and returns its slot to the `src` pool
+    ///
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing this `Box` is returned
+    pub fn transfer<M, R, Q>(self, src: &mut P, dst: &mut Q) -> Result<Box<Q>, Box<P>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::DerefMut<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+    {
+        unsafe {
+            if let Some(index) = dst.pop() {
+                ptr::copy_nonoverlapping(src.slot(self.index), dst.slot(index), 1);
+                src.push(self.index);
 
                 Ok(Box {
                     _not_send_or_sync: PhantomData,
@@ -68,75 +165,223 @@ where
                     index,
                 })
             } else {
-                Err(value)
+                Err(self)
             }
         }
     }
 
-    /// Returns this `Box` to the `pool`
+    /// Allocates a clone of the value on the pool `dst`
     ///
-    /// *NOTE*: This method must be invoked as `Box::free(x, pool)`, `x.free(pool)` doesn't compile.
-    pub fn free(self, pool: &mut P) {
-        unsafe {
-            let p = (pool.memory.as_mut_ptr() as *mut T).add(usize::from(self.index));
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing the clone is returned
+    pub fn try_clone_in<M, R, Q>(&self, dst: &mut Q) -> Result<Box<Q>, AllocError<T>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::DerefMut<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+        T: Clone,
+    {
+        Box::new(dst, T::clone(self))
+    }
 
-            ptr::drop_in_place(p);
+    /// Reserves `n` slots of the `pool` in one go
+    ///
+    /// The slots are turned into `Box`es with `BoxBatch::alloc`.
+    ///
+    /// # Errors
+    ///
+    /// If the pool has less than `n` free slots no slot is reserved and an error containing `n` is
+    /// returned; the error reports how many slots were in use
+    pub fn alloc_many(pool: &mut P, n: u8) -> Result<BoxBatch<'_, P>, AllocError<u8>> {
+        Pool::reserve(pool, n).map_err(|free| {
+            O::on_exhausted();
+
+            AllocError::exhausted(n, N::USIZE - usize::from(free), N::USIZE)
+        })
+    }
 
-            *(p as *mut u8) = pool.head;
+    /// Returns all the `boxes` to the `pool`
+    ///
+    /// The values are dropped and their slots are put back on the free list in one go
+    pub fn free_many<I>(pool: &mut P, boxes: I)
+    where
+        I: IntoIterator<Item = Box<P>>,
+    {
+        let mut first = NIL;
+        let mut last = NIL;
+        let mut count = 0;
+
+        for b in boxes {
+            unsafe {
+                let slot = pool.slot(b.index);
+                ptr::drop_in_place(slot);
+                *(slot as *mut u8) = first;
+            }
+            O::on_free(b.index);
+
+            if last == NIL {
+                last = b.index;
+            }
+            first = b.index;
+            count += 1;
+        }
 
-            pool.free += 1;
-            pool.head = self.index;
+        if count != 0 {
+            unsafe { pool.splice(first, last, count) }
         }
     }
 }
 
-impl<T, N, P> ops::Deref for Box<P>
+impl<T, N, O, P> ops::Deref for Box<P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
 {
     type Target = T;
 
     fn deref(&self) -> &T {
-        unsafe { &*((*P::get()).memory.as_ptr() as *const T).add(usize::from(self.index)) }
+        unsafe { &*Pool::slot_of::<P>(self.index) }
     }
 }
 
-impl<T, N, P> ops::DerefMut for Box<P>
+impl<T, N, O, P> ops::DerefMut for Box<P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
 {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *((*P::get()).memory.as_mut_ptr() as *mut T).add(usize::from(self.index)) }
+        unsafe { &mut *Pool::slot_of::<P>(self.index) }
     }
 }
 
-unsafe impl<T, N, P> Send for Box<P>
+unsafe impl<T, N, O, P> Send for Box<P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
     T: Send,
 {
 }
 
-unsafe impl<T, N, P> Sync for Box<P>
+unsafe impl<T, N, O, P> Sync for Box<P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
     T: Sync,
 {
 }
 
-unsafe impl<T, N, P> StableDeref for Box<P>
+// NOTE moving the `Box` doesn't move the value, which stays in its slot
+impl<P> Unpin for Box<P>
+where
+    P: Singleton,
+{
+}
+
+forward_box_traits!(
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>
+);
+
+/// A batch of slots reserved on the memory pool `P` in one go (see `Box::alloc_many` and
+/// `Pool::reserve`)
+///
+/// A batch also works as a reservation: the slots it holds can't be taken by other users of the
+/// pool.
+///
+/// - `BoxBatch` borrows the pool; the pool can still be used through `BoxBatch::pool`
+/// - `BoxBatch` destructor returns the slots it still holds to the pool
+pub struct BoxBatch<'p, P>
+where
+    P: Singleton,
+    P::Type: sealed::Unreserve,
+{
+    len: u8,
+    pool: &'p mut P,
+}
+
+impl<'p, T, N, O, P> BoxBatch<'p, P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>> + ops::DerefMut<Target = Pool<T, N, O>>,
     N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Moves `value` into one of the slots of the batch
+    ///
+    /// # Panics
+    ///
+    /// This method panics if all the slots of the batch have been used (see `try_alloc`)
+    pub fn alloc(&mut self, value: T) -> Box<P> {
+        assert!(self.len != 0, "all the slots of the batch have been used");
+
+        self.len -= 1;
+
+        unsafe {
+            let index = self.pool.pop_reserved();
+            ptr::write(self.pool.slot(index), value);
+
+            Box {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                index,
+            }
+        }
+    }
+
+    /// Moves `value` into one of the slots of the batch
+    ///
+    /// # Errors
+    ///
+    /// If all the slots of the batch have been used an error containing `value` is returned
+    pub fn try_alloc(&mut self, value: T) -> Result<Box<P>, AllocError<T>> {
+        if self.len == 0 {
+            Err(AllocError::new(value, Cause::ReservationMissing))
+        } else {
+            Ok(self.alloc(value))
+        }
+    }
+}
+
+impl<'p, P> BoxBatch<'p, P>
+where
+    P: Singleton,
+    P::Type: sealed::Unreserve,
+{
+    /// Returns the pool the batch was reserved on
+    ///
+    /// The pool can be used as usual but it can't allocate on the slots left in the batch
+    pub fn pool(&mut self) -> &mut P {
+        self.pool
+    }
+
+    /// Returns the number of slots left in the batch
+    pub fn len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns `true` if all the slots of the batch have been used
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'p, P> Drop for BoxBatch<'p, P>
+where
+    P: Singleton,
+    P::Type: sealed::Unreserve,
 {
+    fn drop(&mut self) {
+        use self::sealed::Unreserve;
+
+        unsafe { (*P::get()).unreserve(self.len) }
+    }
 }
 
 /// A fixed-size memory pool
 ///
+/// Slots are aligned to the alignment of `T`; use `align::Aligned` as `T` to over-align them (e.g.
+/// for DMA buffers)
+///
 /// # Example
 ///
 /// ```
@@ -155,18 +400,20 @@ where
 /// // return the memory to the pool or the memory will be leaked
 /// Box::free(buffer, &mut pool);
 /// ```
-pub struct Pool<T, N>
+pub struct Pool<T, N, O = ()>
 where
     N: ArrayLength<T>,
 {
     _not_send_or_sync: PhantomData<*const ()>,
-    free: u8,
-    head: u8,
-    initialized: u8,
+    _observer: PhantomData<O>,
+    list: FreeList,
     memory: MaybeUninit<GenericArray<T, N>>,
+    // free slots set aside by `BoxBatch`es; they stay on the free list but `pop` doesn't hand them
+    // out
+    reserved: u8,
 }
 
-impl<T, N> Pool<T, N>
+impl<T, N, O> Pool<T, N, O>
 where
     N: ArrayLength<T> + IsLess<U256, Output = True>,
 {
@@ -174,22 +421,380 @@ where
     pub const fn new() -> Self {
         Pool {
             _not_send_or_sync: PhantomData,
-            free: N::U8,
+            _observer: PhantomData,
+            list: FreeList::new(N::U8),
+            memory: MaybeUninit::uninitialized(),
+            reserved: 0,
+        }
+    }
+}
+
+impl<T, N, O> Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Returns an iterator over the values currently allocated on the pool and the indices of
+    /// their slots
+    ///
+    /// # Safety
+    ///
+    /// The values must not be mutated (e.g. through their `Box`es) while the iterator is alive
+    pub unsafe fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            _pool: PhantomData,
+            live: self.occupancy(),
+            memory: self.memory.as_ptr() as *const T,
+        }
+    }
+
+    /// Returns an iterator that allows modifying the values currently allocated on the pool
+    ///
+    /// # Safety
+    ///
+    /// The values must not be accessed through their `Box`es while the iterator is alive
+    pub unsafe fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            _pool: PhantomData,
+            live: self.occupancy(),
+            memory: self.memory.as_mut_ptr() as *mut T,
+        }
+    }
+
+    /// Drops all the values currently allocated on the pool and returns their slots to the pool
+    ///
+    /// Reserved slots stay reserved
+    ///
+    /// # Safety
+    ///
+    /// All the `Box`es allocated on the pool become dangling; they must not be used after this call
+    pub unsafe fn drain(&mut self) {
+        for index in self.occupancy() {
+            ptr::drop_in_place(self.slot(index));
+            self.list.free += 1;
+            O::on_free(index);
+        }
+
+        // NOTE all the slots are free at this point; the reserved ones are still accounted for in
+        // `reserved`
+        let links = self.links();
+        for index in 0..self.list.initialized {
+            links.set_next(index, index + 1);
+        }
+
+        self.list.head = 0;
+    }
+
+    fn occupancy(&self) -> Occupancy {
+        let memory = self.memory.as_ptr() as *const T;
+        let list = self.list;
+
+        unsafe {
+            Occupancy::new(list.head, list.free, list.initialized, |index| {
+                *(memory.add(usize::from(index)) as *const u8)
+            })
+        }
+    }
+
+    /// Reserves `k` slots of the `pool` for the exclusive use of the returned `BoxBatch`
+    ///
+    /// The reserved slots are removed from the pool's free count so other `Box::new` calls can't
+    /// take them. Values are allocated on them with `BoxBatch::alloc`; the slots that are not used
+    /// return to the pool when the `BoxBatch` is dropped.
+    ///
+    /// # Errors
+    ///
+    /// If the pool has less than `k` free slots nothing is reserved and an error containing the
+    /// number of free slots is returned
+    pub fn reserve<P>(pool: &mut P, k: u8) -> Result<BoxBatch<'_, P>, u8>
+    where
+        P: Singleton<Type = Self> + ops::DerefMut<Target = Self>,
+    {
+        assert!(mem::size_of::<T>() > 0);
+
+        let free = pool.list.free - pool.reserved;
+        if k > free {
+            return Err(free);
+        }
+
+        pool.reserved += k;
+
+        Ok(BoxBatch { len: k, pool })
+    }
+
+    /// Returns a snapshot of the usage of the pool
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            capacity: N::U8,
+            live: N::U8 - self.list.free,
+            reserved: self.reserved,
+        }
+    }
+
+    // Takes a slot off the free list; the contents of the slot are left uninitialized
+    pub(crate) unsafe fn pop(&mut self) -> Option<u8> {
+        let links = self.links();
+
+        if self.list.free > self.reserved {
+            let index = self.list.pop(N::U8, &links).unwrap();
+            O::on_alloc(index);
+
+            Some(index)
+        } else {
+            O::on_exhausted();
+
+            None
+        }
+    }
+
+    // Takes one of the reserved slots off the free list
+    unsafe fn pop_reserved(&mut self) -> u8 {
+        let links = self.links();
+
+        self.reserved -= 1;
+        let index = self.list.pop(N::U8, &links).unwrap();
+        O::on_alloc(index);
+
+        index
+    }
+
+    // Puts a slot back on the free list; the value it held must have been dropped or moved out
+    pub(crate) unsafe fn push(&mut self, index: u8) {
+        let links = self.links();
+
+        self.list.push(index, &links);
+        O::on_free(index);
+    }
+
+    // Puts the `count` slots linked from `first` to `last` back on the free list in one go
+    unsafe fn splice(&mut self, first: u8, last: u8, count: u8) {
+        let links = self.links();
+
+        self.list.splice(first, last, count, &links)
+    }
+}
+
+impl<T, N, O> Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+{
+    pub(crate) fn slot(&mut self, index: u8) -> *mut T {
+        unsafe { self.links().add(usize::from(index)) }
+    }
+
+    // Like `slot` but reaches the pool through its singleton, for handles and iterators that don't
+    // hold a reference to the pool
+    pub(crate) fn slot_of<P>(index: u8) -> *mut T
+    where
+        P: Singleton<Type = Self>,
+    {
+        unsafe { (*P::get()).slot(index) }
+    }
+
+    fn links(&mut self) -> *mut T {
+        self.memory.as_mut_ptr() as *mut T
+    }
+}
+
+impl<T, N, O> sealed::Unreserve for Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+{
+    fn unreserve(&mut self, k: u8) {
+        self.reserved -= k;
+    }
+}
+
+mod sealed {
+    pub trait Unreserve {
+        fn unreserve(&mut self, k: u8);
+    }
+}
+
+// Reads and writes the links of a free list, which are stored in the first byte of each free slot
+pub(crate) trait Links {
+    unsafe fn next(&self, index: u8) -> u8;
+
+    unsafe fn set_next(&self, index: u8, next: u8);
+}
+
+impl<T> Links for *mut T {
+    unsafe fn next(&self, index: u8) -> u8 {
+        *(self.add(usize::from(index)) as *const u8)
+    }
+
+    unsafe fn set_next(&self, index: u8, next: u8) {
+        *(self.add(usize::from(index)) as *mut u8) = next
+    }
+}
+
+// The free list of a pool, threaded through its free slots
+//
+// Slots are initialized (linked to the next slot) lazily; the list ends with the slots that have
+// not yet been initialized. This is shared by all the pools of this module, which only differ in
+// how they store this state and reach the slots (`Links`)
+#[derive(Clone, Copy)]
+pub(crate) struct FreeList {
+    pub(crate) free: u8,
+    pub(crate) head: u8,
+    pub(crate) initialized: u8,
+}
+
+impl FreeList {
+    pub(crate) const fn new(capacity: u8) -> Self {
+        FreeList {
+            free: capacity,
             head: 0,
             initialized: 0,
-            memory: MaybeUninit::uninitialized(),
         }
     }
+
+    // Takes a slot off the list
+    pub(crate) unsafe fn pop<L>(&mut self, capacity: u8, links: &L) -> Option<u8>
+    where
+        L: Links,
+    {
+        if self.initialized < capacity {
+            let index = self.initialized;
+
+            links.set_next(index, index + 1);
+            self.initialized += 1;
+        }
+
+        if self.free != 0 {
+            let index = self.head;
+            self.head = links.next(index);
+
+            self.free -= 1;
+
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    // Puts a slot back on the list
+    pub(crate) unsafe fn push<L>(&mut self, index: u8, links: &L)
+    where
+        L: Links,
+    {
+        links.set_next(index, self.head);
+
+        self.free += 1;
+        self.head = index;
+    }
+
+    // Takes `n` slots off the list in one go and returns the first one; the slots stay linked to
+    // each other
+    pub(crate) unsafe fn detach<L>(&mut self, n: u8, links: &L) -> Result<u8, u8>
+    where
+        L: Links,
+    {
+        if n > self.free {
+            return Err(self.free);
+        }
+
+        let first = self.head;
+        let mut index = first;
+        for _ in 0..n {
+            if index == self.initialized {
+                links.set_next(index, index + 1);
+                self.initialized += 1;
+            }
+
+            index = links.next(index);
+        }
+
+        self.head = index;
+        self.free -= n;
+
+        Ok(first)
+    }
+
+    // Puts the `count` slots linked from `first` to `last` back on the list in one go
+    pub(crate) unsafe fn splice<L>(&mut self, first: u8, last: u8, count: u8, links: &L)
+    where
+        L: Links,
+    {
+        links.set_next(last, self.head);
+
+        self.free += count;
+        self.head = first;
+    }
+}
+
+/// Iterator over the values allocated on a `Pool`
+pub struct Iter<'a, T> {
+    _pool: PhantomData<&'a T>,
+    live: Occupancy,
+    memory: *const T,
 }
 
-unsafe impl<T, N> Send for Pool<T, N>
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (u8, &'a T);
+
+    fn next(&mut self) -> Option<(u8, &'a T)> {
+        self.live
+            .next()
+            .map(|index| (index, unsafe { &*self.memory.add(usize::from(index)) }))
+    }
+}
+
+/// Mutable iterator over the values allocated on a `Pool`
+pub struct IterMut<'a, T> {
+    _pool: PhantomData<&'a mut T>,
+    live: Occupancy,
+    memory: *mut T,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (u8, &'a mut T);
+
+    fn next(&mut self) -> Option<(u8, &'a mut T)> {
+        self.live
+            .next()
+            .map(|index| (index, unsafe { &mut *self.memory.add(usize::from(index)) }))
+    }
+}
+
+unsafe impl<T, N, O> Send for Pool<T, N, O>
 where
     N: ArrayLength<T>,
     T: Send,
 {
 }
 
+/// Deserializes a value into a `Box` allocated on the memory pool `P` (see `Box::seed`)
+#[cfg(feature = "serde")]
+pub struct BoxSeed<'a, P>
+where
+    P: Singleton,
+{
+    pool: &'a mut P,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a, T, N, O, P> serde::de::DeserializeSeed<'de> for BoxSeed<'a, P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::DerefMut<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: serde::Deserialize<'de>,
+{
+    type Value = Box<P>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Box<P>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+
+        Box::new(self.pool, value).map_err(serde::de::Error::custom)
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::drop_non_drop, clippy::just_underscores_and_digits)]
 mod tests {
     use core::sync::atomic::{AtomicUsize, Ordering};
 
@@ -197,6 +802,11 @@ mod tests {
     use owned_singleton::Singleton;
 
     use super::{Box, Pool};
+    use crate::{
+        align::{Aligned, A64},
+        error::Cause,
+        observer::PoolObserver,
+    };
 
     #[test]
     fn sanity() {
@@ -208,42 +818,42 @@ mod tests {
         let _0 = Box::new(pool, -1).unwrap();
         assert_eq!(*_0, -1);
         assert_eq!(_0.index, 0);
-        assert_eq!(pool.head, 1);
-        assert_eq!(pool.free, 3);
-        assert_eq!(pool.initialized, 1);
+        assert_eq!(pool.list.head, 1);
+        assert_eq!(pool.list.free, 3);
+        assert_eq!(pool.list.initialized, 1);
 
         let _1 = Box::new(pool, -2).unwrap();
         assert_eq!(*_1, -2);
         assert_eq!(_1.index, 1);
-        assert_eq!(pool.head, 2);
-        assert_eq!(pool.free, 2);
-        assert_eq!(pool.initialized, 2);
+        assert_eq!(pool.list.head, 2);
+        assert_eq!(pool.list.free, 2);
+        assert_eq!(pool.list.initialized, 2);
 
         let _2 = Box::new(pool, -3).unwrap();
         assert_eq!(*_2, -3);
         assert_eq!(_2.index, 2);
-        assert_eq!(pool.head, 3);
-        assert_eq!(pool.free, 1);
-        assert_eq!(pool.initialized, 3);
+        assert_eq!(pool.list.head, 3);
+        assert_eq!(pool.list.free, 1);
+        assert_eq!(pool.list.initialized, 3);
 
         Box::free(_0, pool);
-        assert_eq!(pool.head, 0);
-        assert_eq!(pool.free, 2);
-        assert_eq!(pool.initialized, 3);
+        assert_eq!(pool.list.head, 0);
+        assert_eq!(pool.list.free, 2);
+        assert_eq!(pool.list.initialized, 3);
         assert_eq!(unsafe { *(pool.memory.as_ptr() as *const i8) }, 3);
 
         Box::free(_2, pool);
-        assert_eq!(pool.head, 2);
-        assert_eq!(pool.free, 3);
-        assert_eq!(pool.initialized, 3);
+        assert_eq!(pool.list.head, 2);
+        assert_eq!(pool.list.free, 3);
+        assert_eq!(pool.list.initialized, 3);
         assert_eq!(unsafe { *((pool.memory.as_ptr() as *const i8).add(2)) }, 0);
 
         let _2 = Box::new(pool, -4).unwrap();
         assert_eq!(*_2, -4);
         assert_eq!(_2.index, 2);
-        assert_eq!(pool.head, 0);
-        assert_eq!(pool.free, 2);
-        assert_eq!(pool.initialized, 4);
+        assert_eq!(pool.list.head, 0);
+        assert_eq!(pool.list.free, 2);
+        assert_eq!(pool.list.initialized, 4);
         assert_eq!(unsafe { *((pool.memory.as_ptr() as *const i8).add(3)) }, 4);
     }
 
@@ -251,6 +861,7 @@ mod tests {
     fn destructor() {
         static COUNT: AtomicUsize = AtomicUsize::new(0);
 
+        #[allow(dead_code)]
         pub struct A(usize);
 
         impl A {
@@ -327,4 +938,391 @@ mod tests {
 
         assert!(Box::new(pool, -1).is_err())
     }
+
+    #[test]
+    fn batch() {
+        #[Singleton]
+        static mut P: Pool<i8, U8> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let _0 = Box::new(pool, 0).unwrap();
+
+        // all or nothing
+        let e = Box::alloc_many(pool, 8).err().unwrap();
+        assert_eq!(
+            (e.cause(), e.live(), e.into_inner()),
+            (Cause::Exhausted, Some(1), 8)
+        );
+        assert_eq!(pool.list.free, 7);
+
+        let mut batch = Box::alloc_many(pool, 4).ok().unwrap();
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch.pool().stats().free(), 3);
+
+        let mut xs = std::vec::Vec::new();
+        for i in 1..5 {
+            xs.push(batch.alloc(i));
+        }
+        assert_eq!(
+            batch.try_alloc(5).err().map(|e| e.cause()),
+            Some(Cause::ReservationMissing)
+        );
+        assert!(xs.iter().map(|x| x.index).eq(1..5));
+        drop(batch);
+
+        Box::free_many(pool, xs.drain(2..));
+        assert_eq!((pool.list.free, pool.list.head), (5, 4));
+
+        // unused slots go back to the pool
+        let mut batch = Box::alloc_many(pool, 5).ok().unwrap();
+        let _4 = batch.alloc(-4);
+        assert_eq!(_4.index, 4);
+        drop(batch);
+        assert_eq!((pool.list.free, pool.list.head, pool.reserved), (4, 3, 0));
+
+        Box::free_many(pool, xs.drain(..).chain(Some(_0)).chain(Some(_4)));
+        assert_eq!(pool.list.free, 8);
+        assert_eq!(unsafe { pool.iter().count() }, 0);
+    }
+
+    #[test]
+    fn reserve() {
+        #[Singleton]
+        static mut P: Pool<i8, U2> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        assert_eq!(Pool::reserve(pool, 3).err(), Some(2));
+
+        let mut reservation = Pool::reserve(pool, 1).ok().unwrap();
+
+        // other allocations can't eat into the reservation
+        let _0 = Box::new(reservation.pool(), 0).unwrap();
+        assert!(Box::new(reservation.pool(), -1).is_err());
+
+        let _1 = reservation.alloc(1);
+        assert_eq!((*_1, _1.index), (1, 1));
+        drop(reservation);
+
+        Box::free_many(pool, vec![_0, _1]);
+        assert_eq!(pool.list.free, 2);
+    }
+
+    // reserved slots are neither live nor free
+    #[test]
+    fn reserve_iter() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A(i32);
+
+        impl A {
+            fn new(x: i32) -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A(x)
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<A, U4> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut batch = Box::alloc_many(pool, 2).ok().unwrap();
+        assert_eq!(unsafe { batch.pool().iter().count() }, 0);
+
+        let stats = batch.pool().stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (0, 2, 2));
+
+        let _0 = batch.alloc(A::new(0));
+        let _1 = Box::new(batch.pool(), A::new(1)).ok().unwrap();
+        assert_eq!(
+            unsafe { batch.pool().iter() }
+                .map(|(_, a)| a.0)
+                .collect::<std::vec::Vec<_>>(),
+            [0, 1]
+        );
+
+        let stats = batch.pool().stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (2, 1, 1));
+
+        // only the live values are dropped; the slot left in the batch stays reserved
+        unsafe { batch.pool().drain() }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+
+        let stats = batch.pool().stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (0, 1, 3));
+
+        let mut xs = (0..3)
+            .map(|i| Box::new(batch.pool(), A::new(i)).ok().unwrap())
+            .collect::<std::vec::Vec<_>>();
+        assert!(Box::new(batch.pool(), A::new(3)).is_err());
+        xs.push(batch.alloc(A::new(3)));
+        drop(batch);
+
+        let mut indices = xs.iter().map(|x| x.index).collect::<std::vec::Vec<_>>();
+        indices.sort();
+        assert_eq!(indices, [0, 1, 2, 3]);
+
+        let stats = pool.stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (4, 0, 0));
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn transfer() {
+        #[Singleton]
+        static mut P: Pool<i32, U1> = Pool::new();
+
+        #[Singleton]
+        static mut Q: Pool<i32, U2> = Pool::new();
+
+        let ref mut p = unsafe { P::new() };
+        let ref mut q = unsafe { Q::new() };
+
+        let a = Box::new(p, 0).unwrap();
+        let b = a.try_clone_in(q).unwrap();
+        let c = b.try_clone_in(q).unwrap();
+        assert_eq!(b.try_clone_in(q).err().map(|e| e.into_inner()), Some(0));
+
+        // `dst` is full
+        let a = match a.transfer(p, q) {
+            Err(a) => a,
+            Ok(_) => unreachable!(),
+        };
+        assert_eq!(p.list.free, 0);
+
+        Box::free(c, q);
+        let a = a.transfer(p, q).ok().unwrap();
+        assert_eq!((*a, a.index), (0, 1));
+        assert_eq!((p.list.free, q.list.free), (1, 0));
+
+        Box::free(a, q);
+        Box::free(b, q);
+    }
+
+    #[test]
+    fn traits() {
+        use core::{
+            future::{self, Future, Ready},
+            pin::Pin,
+            ptr,
+            task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+        };
+
+        #[Singleton]
+        static mut P: Pool<i32, U2> = Pool::new();
+
+        #[Singleton]
+        static mut Q: Pool<Ready<i32>, U1> = Pool::new();
+
+        let ref mut p = unsafe { P::new() };
+
+        let a = Box::new(p, 0).unwrap();
+        let b = Box::new(p, 1).unwrap();
+        assert!(a != b && a < b);
+        assert_eq!(format!("{:?} {}", a, b), "0 1");
+
+        Box::free(a, p);
+        Box::free(b, p);
+
+        static VTABLE: RawWakerVTable =
+            RawWakerVTable::new(|_| RawWaker::new(ptr::null(), &VTABLE), |_| {}, |_| {}, |_| {});
+
+        let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) };
+        let ref mut q = unsafe { Q::new() };
+
+        let mut f = Box::new(q, future::ready(42)).ok().unwrap();
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(Pin::new(&mut f).poll(&mut cx), Poll::Ready(42));
+        Box::free(f, q);
+    }
+
+    #[test]
+    fn pin() {
+        use core::{
+            future::Future,
+            marker::PhantomPinned,
+            pin::Pin,
+            ptr,
+            task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+        };
+
+        // a future that must not be moved once polled
+        pub struct Countdown(u8, PhantomPinned);
+
+        impl Future for Countdown {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+                let this = unsafe { self.get_unchecked_mut() };
+
+                if this.0 == 0 {
+                    Poll::Ready(())
+                } else {
+                    this.0 -= 1;
+                    Poll::Pending
+                }
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Countdown, U2> = Pool::new();
+
+        static VTABLE: RawWakerVTable =
+            RawWakerVTable::new(|_| RawWaker::new(ptr::null(), &VTABLE), |_| {}, |_| {}, |_| {});
+
+        let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) };
+        let mut cx = Context::from_waker(&waker);
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut a = Box::pin(pool, Countdown(2, PhantomPinned)).ok().unwrap();
+        let b = Box::new(pool, Countdown(0, PhantomPinned)).ok().unwrap();
+        let mut b = Box::into_pin(b);
+
+        assert_eq!(a.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(b.as_mut().poll(&mut cx), Poll::Ready(()));
+
+        // moving the pinned `Box` doesn't move the future
+        let mut xs = vec![a];
+        assert_eq!(xs[0].as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(xs[0].as_mut().poll(&mut cx), Poll::Ready(()));
+
+        Box::free_pinned(xs.pop().unwrap(), pool);
+        Box::free_pinned(b, pool);
+        assert_eq!(pool.list.free, 2);
+    }
+
+    #[test]
+    fn aligned() {
+        #[Singleton]
+        static mut P: Pool<Aligned<A64, [u8; 3]>, U4> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let _0 = Box::new(pool, Aligned::new([0; 3])).unwrap();
+        let _1 = Box::new(pool, Aligned::new([1; 3])).unwrap();
+
+        let (p0, p1): (&[u8; 3], &[u8; 3]) = (&_0, &_1);
+        assert_eq!(p0.as_ptr() as usize % 64, 0);
+        assert_eq!(p1.as_ptr() as usize - p0.as_ptr() as usize, 64);
+        assert_eq!(*p1, [1; 3]);
+    }
+
+    #[test]
+    fn iter() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A(i32);
+
+        impl A {
+            fn new(x: i32) -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A(x)
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<A, U8> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let _0 = Box::new(pool, A::new(0)).ok().unwrap();
+        let _1 = Box::new(pool, A::new(1)).ok().unwrap();
+        let _2 = Box::new(pool, A::new(2)).ok().unwrap();
+        let _3 = Box::new(pool, A::new(3)).ok().unwrap();
+        Box::free(_0, pool);
+        Box::free(_2, pool);
+        let _4 = Box::new(pool, A::new(4)).ok().unwrap();
+        assert_eq!(_4.index, 2);
+
+        unsafe {
+            for (_, a) in pool.iter_mut() {
+                a.0 *= 10;
+            }
+
+            assert_eq!(
+                pool.iter().map(|(i, a)| (i, a.0)).collect::<std::vec::Vec<_>>(),
+                [(1, 10), (2, 40), (3, 30)]
+            );
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 3);
+
+        unsafe { pool.drain() }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        assert_eq!(unsafe { pool.iter().count() }, 0);
+        assert_eq!(pool.list.free, 8);
+
+        let mut xs = vec![];
+        for i in 0..8 {
+            xs.push(Box::new(pool, A::new(i)).ok().unwrap());
+        }
+        assert_eq!(
+            xs.iter().map(|x| x.index).collect::<std::vec::Vec<_>>(),
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        );
+    }
+
+    #[test]
+    fn observer() {
+        static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+        static EXHAUSTED: AtomicUsize = AtomicUsize::new(0);
+        static FREES: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counter;
+
+        impl PoolObserver for Counter {
+            fn on_alloc(_: u8) {
+                ALLOCS.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn on_free(_: u8) {
+                FREES.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn on_exhausted() {
+                EXHAUSTED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<i8, U2, Counter> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let _0 = Box::new(pool, 0).unwrap();
+        let _1 = Box::new(pool, 1).unwrap();
+        assert!(Box::new(pool, 2).is_err());
+        assert_eq!(ALLOCS.load(Ordering::SeqCst), 2);
+        assert_eq!(EXHAUSTED.load(Ordering::SeqCst), 1);
+
+        Box::free_many(pool, vec![_0, _1]);
+        assert_eq!(FREES.load(Ordering::SeqCst), 2);
+
+        // batches report the slots as the values are moved in
+        let mut batch = Box::alloc_many(pool, 2).ok().unwrap();
+        assert_eq!(ALLOCS.load(Ordering::SeqCst), 2);
+        let _2 = batch.alloc(2);
+        assert_eq!(ALLOCS.load(Ordering::SeqCst), 3);
+
+        assert!(Box::alloc_many(batch.pool(), 1).is_err());
+        assert_eq!(EXHAUSTED.load(Ordering::SeqCst), 2);
+
+        drop(batch);
+        Box::free(_2, pool);
+        assert_eq!(FREES.load(Ordering::SeqCst), 3);
+    }
 }
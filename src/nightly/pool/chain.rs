@@ -0,0 +1,337 @@
+//! Chaining of memory pools: allocations overflow into a fallback pool when the primary one has
+//! been exhausted
+
+use core::{fmt, ops};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Box, Pool};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A memory allocator for values of type `T`
+pub trait Alloc<T> {
+    /// Handle to a value allocated by this allocator
+    type Box: ops::DerefMut<Target = T>;
+
+    /// Allocates the given `value`
+    ///
+    /// # Errors
+    ///
+    /// If the allocator has run out of memory an error containing `value` is returned
+    fn alloc(&mut self, value: T) -> Result<Self::Box, AllocError<T>>;
+
+    /// Like `alloc` but doesn't report exhaustion to the allocator's observer, if any
+    ///
+    /// `Chain` uses this so that exhaustion is only reported when none of the chained allocators
+    /// could satisfy the allocation.
+    fn alloc_unreported(&mut self, value: T) -> Result<Self::Box, AllocError<T>> {
+        self.alloc(value)
+    }
+
+    /// Reports to the allocator's observer, if any, that the allocator has been exhausted
+    fn report_exhausted(&self) {}
+
+    /// Drops the value and returns its memory to the allocator
+    fn free(&mut self, b: Self::Box);
+}
+
+impl<T, N, O, P> Alloc<T> for P
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::DerefMut<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    type Box = Box<P>;
+
+    fn alloc(&mut self, value: T) -> Result<Box<P>, AllocError<T>> {
+        Box::new(self, value)
+    }
+
+    fn alloc_unreported(&mut self, value: T) -> Result<Box<P>, AllocError<T>> {
+        // NOTE checked up front because `Box::new` reports the exhaustion
+        if self.stats().free() == 0 {
+            Err(AllocError::exhausted(value, N::USIZE, N::USIZE))
+        } else {
+            Box::new(self, value)
+        }
+    }
+
+    fn report_exhausted(&self) {
+        O::on_exhausted()
+    }
+
+    fn free(&mut self, b: Box<P>) {
+        Box::free(b, self)
+    }
+}
+
+/// Two allocators chained together: allocations that can't be satisfied by the `primary`
+/// allocator are handed to the `fallback` allocator
+///
+/// Chains can be nested to get more than one level of fallback.
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{chain::{Alloc, Chain, Chained}, Pool},
+/// };
+///
+/// // e.g. fast on-chip SRAM
+/// #[Singleton]
+/// static mut FAST: Pool<[u8; 64], U1> = Pool::new();
+///
+/// // e.g. external SDRAM
+/// #[Singleton]
+/// static mut SLOW: Pool<[u8; 64], U8> = Pool::new();
+///
+/// let mut pools = Chain::new(unsafe { FAST::new() }, unsafe { SLOW::new() });
+///
+/// let a = pools.alloc([0; 64]).unwrap();
+/// let b = pools.alloc([1; 64]).unwrap();
+///
+/// match (&a, &b) {
+///     (Chained::Primary(_), Chained::Fallback(_)) => {}
+///     _ => unreachable!(),
+/// }
+///
+/// // each `Box` goes back to the pool it was allocated on
+/// pools.free(a);
+/// pools.free(b);
+/// ```
+pub struct Chain<A, B> {
+    fallback: B,
+    primary: A,
+}
+
+impl<A, B> Chain<A, B> {
+    /// Chains the `primary` allocator with the `fallback` allocator
+    pub fn new(primary: A, fallback: B) -> Self {
+        Chain { fallback, primary }
+    }
+
+    /// Returns the chained allocators: `(primary, fallback)`
+    pub fn split(self) -> (A, B) {
+        (self.primary, self.fallback)
+    }
+}
+
+impl<T, A, B> Alloc<T> for Chain<A, B>
+where
+    A: Alloc<T>,
+    B: Alloc<T>,
+{
+    type Box = Chained<A::Box, B::Box>;
+
+    // NOTE if both allocators fail the error of the `fallback` allocator is returned and both
+    // allocators report the exhaustion; if only the `primary` allocator fails nothing is reported
+    fn alloc(&mut self, value: T) -> Result<Self::Box, AllocError<T>> {
+        self.alloc_unreported(value).map_err(|e| {
+            self.report_exhausted();
+
+            e
+        })
+    }
+
+    fn alloc_unreported(&mut self, value: T) -> Result<Self::Box, AllocError<T>> {
+        match self.primary.alloc_unreported(value) {
+            Ok(b) => Ok(Chained::Primary(b)),
+            Err(e) => self
+                .fallback
+                .alloc_unreported(e.into_inner())
+                .map(Chained::Fallback),
+        }
+    }
+
+    fn report_exhausted(&self) {
+        self.primary.report_exhausted();
+        self.fallback.report_exhausted();
+    }
+
+    fn free(&mut self, b: Self::Box) {
+        match b {
+            Chained::Primary(b) => self.primary.free(b),
+            Chained::Fallback(b) => self.fallback.free(b),
+        }
+    }
+}
+
+/// A value allocated on a `Chain` of allocators
+///
+/// The variant records which allocator the value was allocated on
+pub enum Chained<A, B> {
+    /// The value was allocated on the primary allocator
+    Primary(A),
+    /// The value was allocated on the fallback allocator
+    Fallback(B),
+}
+
+impl<T, A, B> ops::Deref for Chained<A, B>
+where
+    A: ops::Deref<Target = T>,
+    B: ops::Deref<Target = T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Chained::Primary(b) => b,
+            Chained::Fallback(b) => b,
+        }
+    }
+}
+
+impl<T, A, B> ops::DerefMut for Chained<A, B>
+where
+    A: ops::DerefMut<Target = T>,
+    B: ops::DerefMut<Target = T>,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        match self {
+            Chained::Primary(b) => b,
+            Chained::Fallback(b) => b,
+        }
+    }
+}
+
+impl<T, A, B> fmt::Debug for Chained<A, B>
+where
+    A: ops::Deref<Target = T>,
+    B: ops::Deref<Target = T>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+/// The global allocator
+///
+/// Use this as the last fallback of a `Chain` to spill over to the heap. This allocator never runs
+/// out of memory (it aborts instead).
+#[cfg(feature = "alloc")]
+pub struct Heap;
+
+#[cfg(feature = "alloc")]
+impl<T> Alloc<T> for Heap {
+    type Box = alloc::boxed::Box<T>;
+
+    fn alloc(&mut self, value: T) -> Result<alloc::boxed::Box<T>, AllocError<T>> {
+        Ok(alloc::boxed::Box::new(value))
+    }
+
+    fn free(&mut self, b: alloc::boxed::Box<T>) {
+        drop(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Alloc, Chain, Chained};
+    use crate::nightly::pool::Pool;
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<i32, U1> = Pool::new();
+
+        #[Singleton]
+        static mut Q: Pool<i32, U2> = Pool::new();
+
+        let mut pools = Chain::new(unsafe { P::new() }, unsafe { Q::new() });
+
+        let a = pools.alloc(0).unwrap();
+        let b = pools.alloc(1).unwrap();
+        let c = pools.alloc(2).unwrap();
+        assert_eq!(pools.alloc(3).err().map(|e| e.into_inner()), Some(3));
+
+        assert!(match a {
+            Chained::Primary(ref a) => a.index == 0,
+            _ => false,
+        });
+        assert!(match c {
+            Chained::Fallback(ref c) => c.index == 1,
+            _ => false,
+        });
+        assert_eq!((*a, *b, *c), (0, 1, 2));
+
+        // freeing a `Box` makes room in *its* pool
+        pools.free(b);
+
+        let d = pools.alloc(4).unwrap();
+        assert!(match d {
+            Chained::Fallback(ref d) => d.index == 0,
+            _ => false,
+        });
+
+        pools.free(a);
+
+        let (p, q) = pools.split();
+        assert_eq!(p.list.free, 1);
+        assert_eq!(q.list.free, 0);
+    }
+
+    #[test]
+    fn observer() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::observer::PoolObserver;
+
+        static EXHAUSTED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counter;
+
+        impl PoolObserver for Counter {
+            fn on_exhausted() {
+                EXHAUSTED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<i32, U1, Counter> = Pool::new();
+
+        #[Singleton]
+        static mut Q: Pool<i32, U1> = Pool::new();
+
+        let mut pools = Chain::new(unsafe { P::new() }, unsafe { Q::new() });
+
+        // overflowing into the fallback pool is not an exhaustion of the chain
+        let a = pools.alloc(0).unwrap();
+        let b = pools.alloc(1).unwrap();
+        assert_eq!(EXHAUSTED.load(Ordering::SeqCst), 0);
+
+        assert!(pools.alloc(2).is_err());
+        assert_eq!(EXHAUSTED.load(Ordering::SeqCst), 1);
+
+        pools.free(b);
+        pools.free(a);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn heap() {
+        use super::Heap;
+
+        #[Singleton]
+        static mut P: Pool<i32, U1> = Pool::new();
+
+        let mut pools = Chain::new(unsafe { P::new() }, Heap);
+
+        let a = pools.alloc(0).unwrap();
+        let b = pools.alloc(1).unwrap();
+
+        assert!(match (&a, &b) {
+            (Chained::Primary(_), Chained::Fallback(_)) => true,
+            _ => false,
+        });
+
+        pools.free(b);
+        pools.free(a);
+    }
+}
@@ -0,0 +1,238 @@
+//! Double-ended queue whose nodes are allocated on a memory pool
+
+use core::{fmt, ops};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{
+    list::{Iter, IterMut, List, Node},
+    Pool,
+};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A double-ended queue whose nodes are allocated on the memory pool `P`
+///
+/// This is a `List` without handles, and thus without `unsafe` methods. Several queues can share
+/// the same pool.
+///
+/// - `Deque` must be explicitly freed (`Deque::free`) or the nodes it holds will be leaked
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{deque::Deque, list::Node, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Node<u32>, U2> = Pool::new();
+///
+/// let mut pool = unsafe { P::new() };
+///
+/// let mut queue: Deque<P> = Deque::new();
+///
+/// queue.push_back(&mut pool, 1).unwrap();
+/// queue.push_back(&mut pool, 2).unwrap();
+/// assert_eq!(queue.push_back(&mut pool, 3).err().unwrap().into_inner(), 3);
+///
+/// assert_eq!(queue.pop_front(&mut pool), Some(1));
+///
+/// // return the nodes to the pool or the memory will be leaked
+/// queue.free(&mut pool);
+/// ```
+pub struct Deque<P>
+where
+    P: Singleton,
+{
+    list: List<P>,
+}
+
+impl<T, N, O, P> Deque<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>> + ops::DerefMut<Target = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    O: PoolObserver,
+{
+    /// Creates an empty queue; this doesn't allocate
+    pub fn new() -> Self {
+        Deque { list: List::new() }
+    }
+
+    /// Prepends `value` to the queue
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn push_front(&mut self, pool: &mut P, value: T) -> Result<(), AllocError<T>> {
+        self.list.push_front(pool, value).map(drop)
+    }
+
+    /// Appends `value` to the queue
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn push_back(&mut self, pool: &mut P, value: T) -> Result<(), AllocError<T>> {
+        self.list.push_back(pool, value).map(drop)
+    }
+
+    /// Removes the first element of the queue and returns it, or `None` if it's empty
+    pub fn pop_front(&mut self, pool: &mut P) -> Option<T> {
+        self.list.pop_front(pool)
+    }
+
+    /// Removes the last element of the queue and returns it, or `None` if it's empty
+    pub fn pop_back(&mut self, pool: &mut P) -> Option<T> {
+        self.list.pop_back(pool)
+    }
+
+    /// Removes all the elements of the queue and returns its nodes to the `pool`
+    pub fn clear(&mut self, pool: &mut P) {
+        self.list.clear(pool)
+    }
+
+    /// Drops all the elements of the queue and returns its nodes to the `pool`
+    pub fn free(self, pool: &mut P) {
+        self.list.free(pool)
+    }
+}
+
+impl<T, N, O, P> Deque<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+{
+    /// Returns the number of elements in the queue
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    /// Returns `true` if the queue contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Returns a reference to the first element of the queue, or `None` if it's empty
+    pub fn front(&self) -> Option<&T> {
+        self.list.front()
+    }
+
+    /// Returns a mutable reference to the first element of the queue, or `None` if it's empty
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.list.front_mut()
+    }
+
+    /// Returns a reference to the last element of the queue, or `None` if it's empty
+    pub fn back(&self) -> Option<&T> {
+        self.list.back()
+    }
+
+    /// Returns a mutable reference to the last element of the queue, or `None` if it's empty
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.list.back_mut()
+    }
+
+    /// Returns a front-to-back iterator over the elements of the queue
+    pub fn iter(&self) -> Iter<'_, P> {
+        self.list.iter()
+    }
+
+    /// Returns a front-to-back iterator that allows modifying the elements of the queue
+    pub fn iter_mut(&mut self) -> IterMut<'_, P> {
+        self.list.iter_mut()
+    }
+}
+
+impl<T, N, O, P> Default for Deque<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>> + ops::DerefMut<Target = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    O: PoolObserver,
+{
+    fn default() -> Self {
+        Deque::new()
+    }
+}
+
+impl<T, N, O, P> fmt::Debug for Deque<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.list.fmt(f)
+    }
+}
+
+impl<'a, T, N, O, P> IntoIterator for &'a Deque<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, P>;
+
+    fn into_iter(self) -> Iter<'a, P> {
+        self.iter()
+    }
+}
+
+impl<'a, T, N, O, P> IntoIterator for &'a mut Deque<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, P>;
+
+    fn into_iter(self) -> IterMut<'a, P> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::Deque;
+    use crate::{
+        error::Cause,
+        nightly::pool::{list::Node, Pool},
+    };
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Node<i32>, U3> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut q: Deque<P> = Deque::new();
+        let mut r: Deque<P> = Deque::new();
+
+        q.push_back(pool, 1).unwrap();
+        q.push_front(pool, 0).unwrap();
+        r.push_back(pool, 2).unwrap();
+        let e = q.push_back(pool, 3).err().unwrap();
+        assert_eq!((e.cause(), e.into_inner()), (Cause::Exhausted, 3));
+        assert_eq!(format!("{:?}", q), "[0, 1]");
+
+        *q.back_mut().unwrap() += 10;
+        assert_eq!(q.back(), Some(&11));
+
+        assert_eq!(q.pop_back(pool), Some(11));
+        assert_eq!(q.pop_back(pool), Some(0));
+        assert_eq!(q.pop_front(pool), None);
+        assert!(q.is_empty());
+
+        r.free(pool);
+        assert_eq!(pool.list.free, 3);
+    }
+}
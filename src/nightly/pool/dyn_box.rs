@@ -0,0 +1,347 @@
+//! Trait objects allocated on a memory pool
+
+use core::{
+    any::{Any, TypeId},
+    fmt,
+    marker::{PhantomData, Unsize},
+    mem, ops,
+    ptr::{self, Pointee},
+};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::Pool;
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+};
+
+/// A value of (possibly) unsized type `D`, e.g. `dyn Trait`, allocated on the memory pool `P`
+///
+/// Any value of a type `U: Unsize<D>` can be stored in a `DynBox` as long as it fits in a slot of
+/// the pool, i.e. its size and alignment are not greater than the size and alignment of the pool's
+/// element type. Use a byte array wrapped in `align::Aligned` to pick the slot layout.
+///
+/// - `DynBox` must be explicitly deallocated or memory will be leaked
+/// - `DynBox<P, D>` only stores the slot index and the pointer metadata (e.g. the vtable) of `D`:
+///   `sizeof(DynBox<_, dyn Trait>)` is two words and `sizeof(DynBox<_, U>)`, with `U` sized, is a
+///   single byte
+/// - `DynBox<P, D>` implements `Send` if `D` implements `Send`
+/// - `DynBox<P, D>` implements `Sync` if `D` implements `Sync`
+///
+/// # Example
+///
+/// ```
+/// use core::any::Any;
+///
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::{
+///     align::{Aligned, A8},
+///     nightly::{consts::*, pool::{dyn_box::DynBox, Pool}},
+/// };
+///
+/// trait Handler {
+///     fn handle(&mut self) -> u32;
+/// }
+///
+/// struct Counter(u32);
+///
+/// impl Handler for Counter {
+///     fn handle(&mut self) -> u32 {
+///         self.0 += 1;
+///         self.0
+///     }
+/// }
+///
+/// struct Constant(u64);
+///
+/// impl Handler for Constant {
+///     fn handle(&mut self) -> u32 {
+///         self.0 as u32
+///     }
+/// }
+///
+/// #[Singleton]
+/// static mut P: Pool<Aligned<A8, [u8; 16]>, U4> = Pool::new();
+///
+/// let mut pool = unsafe { P::new() };
+///
+/// let mut a: DynBox<P, dyn Handler> = DynBox::new(&mut pool, Counter(0)).ok().unwrap();
+/// let mut b: DynBox<P, dyn Handler> = DynBox::new(&mut pool, Constant(42)).ok().unwrap();
+///
+/// assert_eq!(a.handle(), 1);
+/// assert_eq!(b.handle(), 42);
+///
+/// // doesn't fit in a slot
+/// assert!(DynBox::<P, dyn Any>::new(&mut pool, [0u64; 3]).is_err());
+///
+/// a.free(&mut pool);
+/// b.free(&mut pool);
+/// ```
+pub struct DynBox<P, D>
+where
+    P: Singleton,
+    D: ?Sized,
+{
+    _pool: PhantomData<P>,
+    index: u8,
+    metadata: <D as Pointee>::Metadata,
+}
+
+impl<T, N, O, P, D> DynBox<P, D>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    D: ?Sized,
+{
+    fn ptr(&self) -> *mut D {
+        ptr::from_raw_parts_mut(Pool::slot_of::<P>(self.index) as *mut (), self.metadata)
+    }
+}
+
+impl<T, N, O, P, D> DynBox<P, D>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::DerefMut<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    D: ?Sized,
+{
+    /// Allocates the given `value` on the pool
+    ///
+    /// # Errors
+    ///
+    /// If the `value` doesn't fit in a slot of the pool, or the memory pool has been exhausted, an
+    /// error containing `value` is returned
+    pub fn new<U>(pool: &mut P, value: U) -> Result<Self, AllocError<U>>
+    where
+        U: Unsize<D>,
+    {
+        assert!(mem::size_of::<T>() > 0);
+
+        if mem::size_of::<U>() > mem::size_of::<T>() || mem::align_of::<U>() > mem::align_of::<T>()
+        {
+            return Err(AllocError::new(value, Cause::LayoutMismatch));
+        }
+
+        unsafe {
+            if let Some(index) = pool.pop() {
+                let slot = pool.slot(index) as *mut U;
+                ptr::write(slot, value);
+
+                Ok(DynBox {
+                    _pool: PhantomData,
+                    index,
+                    metadata: ptr::metadata(slot as *mut D),
+                })
+            } else {
+                Err(AllocError::exhausted(value, N::USIZE, N::USIZE))
+            }
+        }
+    }
+
+    /// Drops the value and returns its slot to the `pool`
+    pub fn free(self, pool: &mut P) {
+        unsafe {
+            ptr::drop_in_place(self.ptr());
+
+            pool.push(self.index);
+        }
+    }
+}
+
+impl<T, N, O, P> DynBox<P, dyn Any>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+{
+    /// Attempts to downcast the value to the concrete type `U`
+    ///
+    /// # Errors
+    ///
+    /// If the value is not of type `U` this `DynBox` is returned unchanged
+    pub fn downcast<U>(self) -> Result<DynBox<P, U>, Self>
+    where
+        U: Any,
+    {
+        if (*self).type_id() == TypeId::of::<U>() {
+            Ok(DynBox {
+                _pool: PhantomData,
+                index: self.index,
+                metadata: (),
+            })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<T, N, O, P> DynBox<P, dyn Any + Send>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+{
+    /// Attempts to downcast the value to the concrete type `U`
+    ///
+    /// # Errors
+    ///
+    /// If the value is not of type `U` this `DynBox` is returned unchanged
+    pub fn downcast<U>(self) -> Result<DynBox<P, U>, Self>
+    where
+        U: Any,
+    {
+        if (*self).type_id() == TypeId::of::<U>() {
+            Ok(DynBox {
+                _pool: PhantomData,
+                index: self.index,
+                metadata: (),
+            })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<T, N, O, P, D> ops::Deref for DynBox<P, D>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    D: ?Sized,
+{
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        unsafe { &*self.ptr() }
+    }
+}
+
+impl<T, N, O, P, D> ops::DerefMut for DynBox<P, D>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    D: ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut D {
+        unsafe { &mut *self.ptr() }
+    }
+}
+
+impl<T, N, O, P, D> fmt::Debug for DynBox<P, D>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    D: fmt::Debug + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        D::fmt(self, f)
+    }
+}
+
+unsafe impl<P, D> Send for DynBox<P, D>
+where
+    P: Singleton,
+    D: Send + ?Sized,
+{
+}
+
+unsafe impl<P, D> Sync for DynBox<P, D>
+where
+    P: Singleton,
+    D: Sync + ?Sized,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        any::Any,
+        fmt::Debug,
+        mem,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::DynBox;
+    use crate::{
+        align::{Aligned, A4},
+        error::Cause,
+        nightly::pool::Pool,
+    };
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Aligned<A4, [u8; 8]>, U2> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let a: DynBox<P, dyn Debug> = DynBox::new(pool, 1u8).unwrap();
+        let b: DynBox<P, dyn Debug> = DynBox::new(pool, (2u16, 3u32)).unwrap();
+        assert_eq!(format!("{:?} {:?}", a, b), "1 (2, 3)");
+
+        // too big
+        let e = DynBox::<P, dyn Debug>::new(pool, [0u8; 9]).err().unwrap();
+        assert_eq!(e.cause(), Cause::LayoutMismatch);
+        assert_eq!(e.into_inner(), [0; 9]);
+        // over-aligned
+        let e = DynBox::<P, dyn Debug>::new(pool, 0u64).err().unwrap();
+        assert_eq!(e.cause(), Cause::LayoutMismatch);
+
+        a.free(pool);
+        b.free(pool);
+        assert_eq!(pool.list.free, 2);
+    }
+
+    #[test]
+    fn size() {
+        #[Singleton]
+        static mut P: Pool<Aligned<A4, [u8; 8]>, U2> = Pool::new();
+
+        // only the vtable is stored next to the slot index
+        assert_eq!(
+            mem::size_of::<DynBox<P, dyn Debug>>(),
+            2 * mem::size_of::<usize>()
+        );
+        assert_eq!(mem::size_of::<DynBox<P, i32>>(), 1);
+    }
+
+    #[test]
+    fn downcast() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A;
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Aligned<A4, [u8; 4]>, U2> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let a: DynBox<P, dyn Any> = DynBox::new(pool, A).ok().unwrap();
+        let b: DynBox<P, dyn Any> = DynBox::new(pool, 42i32).unwrap();
+
+        let a = a.downcast::<i32>().err().unwrap();
+        let b = b.downcast::<i32>().unwrap();
+        assert_eq!(*b, 42);
+
+        // the destructor of the erased type runs
+        a.free(pool);
+        assert_eq!(COUNT.load(Ordering::SeqCst), 1);
+
+        b.free(pool);
+        assert_eq!(pool.list.free, 2);
+
+        let a: DynBox<P, dyn Any + Send> = DynBox::new(pool, 1u16).unwrap();
+        let a = a.downcast::<i32>().err().unwrap();
+        let a = a.downcast::<u16>().unwrap();
+        assert_eq!(*a, 1);
+        a.free(pool);
+    }
+}
@@ -0,0 +1,746 @@
+//! Hash map whose entries are stored in a memory pool
+
+use core::{
+    borrow::Borrow,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops, ptr,
+};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Pool, NIL};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A slot of a `HashMap`
+///
+/// This is the element type of the memory pool that backs a `HashMap`
+#[repr(C)]
+pub struct Node<K, V> {
+    // NOTE the pool stores the index of the next free slot in the first byte of the slot
+    next: u8,
+    // first entry of the bucket that has the same index as this slot; valid whether or not the
+    // slot is in use
+    head: u8,
+    entry: MaybeUninit<(K, V)>,
+}
+
+/// A hash map that stores its entries on the memory pool `P`
+///
+/// - The map has one bucket per pool slot; entries whose keys hash to the same bucket are chained
+///   through their slot indices
+/// - The map owns the pool: the bucket heads live in the pool slots
+/// - Keys are hashed with the `BuildHasher` `S`
+///
+/// *NOTE*: Entries that are still in the map when it's dropped are leaked; use `into_pool` to drop
+/// them
+///
+/// # Example
+///
+/// ```
+/// use std::collections::hash_map::RandomState;
+///
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{hash_map::{HashMap, Node}, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Node<[u8; 4], u32>, U2> = Pool::new();
+///
+/// let mut connections: HashMap<P, RandomState> = HashMap::new(unsafe { P::new() });
+///
+/// assert_eq!(connections.insert([10, 0, 0, 1], 0).unwrap(), None);
+/// assert_eq!(connections.insert([10, 0, 0, 2], 0).unwrap(), None);
+///
+/// // the pool has been exhausted
+/// let e = connections.insert([10, 0, 0, 3], 0).unwrap_err();
+/// assert_eq!(e.into_inner(), ([10, 0, 0, 3], 0));
+///
+/// *connections.entry([10, 0, 0, 1]).or_insert(0).unwrap() += 1;
+/// assert_eq!(connections.get(&[10, 0, 0, 1]), Some(&1));
+///
+/// assert_eq!(connections.remove(&[10, 0, 0, 2]), Some(0));
+/// assert_eq!(connections.len(), 1);
+/// ```
+pub struct HashMap<P, S>
+where
+    P: Singleton,
+{
+    hash_builder: S,
+    pool: P,
+}
+
+impl<K, V, N, O, P, S> HashMap<P, S>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::DerefMut<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    O: PoolObserver,
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Creates an empty map that stores its entries on the given `pool`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the `pool` has already been used to allocate `Box`es
+    pub fn new(pool: P) -> Self
+    where
+        S: Default,
+    {
+        HashMap::with_hasher(pool, S::default())
+    }
+
+    /// Creates an empty map that stores its entries on the given `pool` and hashes its keys with
+    /// `hash_builder`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the `pool` has already been used to allocate `Box`es
+    pub fn with_hasher(mut pool: P, hash_builder: S) -> Self {
+        assert_eq!(pool.list.initialized, 0);
+
+        for index in 0..N::U8 {
+            unsafe { (*pool.slot(index)).head = NIL }
+        }
+
+        HashMap { hash_builder, pool }
+    }
+
+    /// Inserts the `key`-`value` pair into the map
+    ///
+    /// If the map already contained `key` its value is replaced and the old value is returned
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `key` and `value` is returned
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, AllocError<(K, V)>> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(value))),
+            Entry::Vacant(entry) => entry.insert(value).map(|_| None),
+        }
+    }
+
+    /// Returns the entry of `key`, for in-place manipulation
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, P, S> {
+        let (bucket, prev, index) = self.locate(&key);
+
+        if index == NIL {
+            Entry::Vacant(VacantEntry {
+                bucket,
+                key,
+                map: self,
+            })
+        } else {
+            Entry::Occupied(OccupiedEntry {
+                bucket,
+                index,
+                map: self,
+                prev,
+            })
+        }
+    }
+
+    /// Returns a mutable reference to the value associated to `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (_, _, index) = self.locate(key);
+
+        if index == NIL {
+            None
+        } else {
+            unsafe { Some(&mut (*(*self.pool.slot(index)).entry.as_mut_ptr()).1) }
+        }
+    }
+
+    /// Removes `key` from the map and returns its value
+    ///
+    /// Returns `None` if the map didn't contain `key`
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes `key` from the map and returns the stored key and its value
+    ///
+    /// Returns `None` if the map didn't contain `key`
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (bucket, prev, index) = self.locate(key);
+
+        if index == NIL {
+            None
+        } else {
+            unsafe { Some(self.unlink(bucket, prev, index)) }
+        }
+    }
+
+    /// Removes all the entries from the map
+    pub fn clear(&mut self) {
+        for bucket in 0..N::U8 {
+            unsafe {
+                while (*self.pool.slot(bucket)).head != NIL {
+                    let index = (*self.pool.slot(bucket)).head;
+                    drop(self.unlink(bucket, NIL, index));
+                }
+            }
+        }
+    }
+
+    /// Drops all the entries of the map and returns the pool
+    ///
+    /// The returned pool can still allocate `Box`es but it can't back a new map
+    pub fn into_pool(mut self) -> P {
+        self.clear();
+        self.pool
+    }
+
+    /// Returns an iterator over the entries of the map that allows modifying the values
+    pub fn iter_mut(&mut self) -> IterMut<'_, P> {
+        IterMut {
+            _map: PhantomData,
+            bucket: 0,
+            index: NIL,
+            len: self.len(),
+        }
+    }
+
+    // Allocates a slot for the entry and pushes it to the front of the `bucket` chain
+    fn link(&mut self, bucket: u8, key: K, value: V) -> Result<u8, AllocError<(K, V)>> {
+        unsafe {
+            if let Some(index) = self.pool.pop() {
+                let head = (*self.pool.slot(bucket)).head;
+
+                let node = self.pool.slot(index);
+                (*node).next = head;
+                ptr::write((*node).entry.as_mut_ptr(), (key, value));
+
+                (*self.pool.slot(bucket)).head = index;
+
+                Ok(index)
+            } else {
+                Err(AllocError::exhausted((key, value), N::USIZE, N::USIZE))
+            }
+        }
+    }
+
+    // Removes the entry `index`, which follows `prev` in the `bucket` chain, and returns its slot
+    // to the pool
+    unsafe fn unlink(&mut self, bucket: u8, prev: u8, index: u8) -> (K, V) {
+        let node = self.pool.slot(index);
+        let next = (*node).next;
+        let entry = ptr::read((*node).entry.as_ptr());
+
+        if prev == NIL {
+            (*self.pool.slot(bucket)).head = next;
+        } else {
+            (*self.pool.slot(prev)).next = next;
+        }
+
+        self.pool.push(index);
+
+        entry
+    }
+}
+
+impl<K, V, N, O, P, S> HashMap<P, S>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::Deref<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns `true` if the map contains `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value associated to `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (_, _, index) = self.locate(key);
+
+        if index == NIL {
+            None
+        } else {
+            unsafe { Some(&(*self.get_node(index).entry.as_ptr()).1) }
+        }
+    }
+
+    /// Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        N::USIZE - usize::from(self.pool.list.free)
+    }
+
+    /// Returns `true` if the map contains no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of entries the map can hold
+    pub fn capacity(&self) -> usize {
+        N::USIZE
+    }
+
+    /// Returns an iterator over the entries of the map, in arbitrary order
+    pub fn iter(&self) -> Iter<'_, P> {
+        Iter {
+            _map: PhantomData,
+            bucket: 0,
+            index: NIL,
+            len: self.len(),
+        }
+    }
+
+    // Returns the bucket of `key`, the entry that precedes `key` in the bucket chain and the entry
+    // of `key`; the last two are `NIL` if not found
+    fn locate<Q>(&self, key: &Q) -> (u8, u8, u8)
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let mut hasher = self.hash_builder.build_hasher();
+        key.hash(&mut hasher);
+        let bucket = (hasher.finish() % N::U64) as u8;
+
+        let mut prev = NIL;
+        let mut index = self.get_node(bucket).head;
+        while index != NIL {
+            let node = self.get_node(index);
+            if unsafe { (*node.entry.as_ptr()).0.borrow() } == key {
+                break;
+            }
+
+            prev = index;
+            index = node.next;
+        }
+
+        (bucket, prev, index)
+    }
+
+    fn get_node(&self, index: u8) -> &Node<K, V> {
+        unsafe { &*(self.pool.memory.as_ptr() as *const Node<K, V>).add(usize::from(index)) }
+    }
+}
+
+impl<K, V, N, O, P, S> fmt::Debug for HashMap<P, S>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::Deref<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    K: Eq + Hash + fmt::Debug,
+    S: BuildHasher,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// A view into a single entry of a `HashMap` (see `HashMap::entry`)
+pub enum Entry<'a, K, P, S>
+where
+    P: Singleton,
+{
+    /// The map contains the key
+    Occupied(OccupiedEntry<'a, P, S>),
+    /// The map doesn't contain the key
+    Vacant(VacantEntry<'a, K, P, S>),
+}
+
+impl<'a, K, V, N, O, P, S> Entry<'a, K, P, S>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::DerefMut<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    O: PoolObserver,
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns a reference to the key of the entry
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Returns a mutable reference to the value of the entry, inserting `default` if it's vacant
+    ///
+    /// # Errors
+    ///
+    /// If the entry is vacant and the memory pool has been exhausted an error containing the key
+    /// and `default` is returned
+    pub fn or_insert(self, default: V) -> Result<&'a mut V, AllocError<(K, V)>> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns a mutable reference to the value of the entry, inserting the value returned by
+    /// `default` if it's vacant
+    ///
+    /// # Errors
+    ///
+    /// If the entry is vacant and the memory pool has been exhausted an error containing the key
+    /// and the value returned by `default` is returned
+    pub fn or_insert_with<F>(self, default: F) -> Result<&'a mut V, AllocError<(K, V)>>
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Calls `f` on the value of the entry if it's occupied
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(ref mut entry) = self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+}
+
+/// An entry of a `HashMap` that contains a value
+pub struct OccupiedEntry<'a, P, S>
+where
+    P: Singleton,
+{
+    bucket: u8,
+    index: u8,
+    map: &'a mut HashMap<P, S>,
+    prev: u8,
+}
+
+impl<'a, K, V, N, O, P, S> OccupiedEntry<'a, P, S>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::DerefMut<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    O: PoolObserver,
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns a reference to the key of the entry
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.map.get_node(self.index).entry.as_ptr()).0 }
+    }
+
+    /// Returns a reference to the value of the entry
+    pub fn get(&self) -> &V {
+        unsafe { &(*self.map.get_node(self.index).entry.as_ptr()).1 }
+    }
+
+    /// Returns a mutable reference to the value of the entry
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut (*(*self.map.pool.slot(self.index)).entry.as_mut_ptr()).1 }
+    }
+
+    /// Converts the entry into a mutable reference to its value, bound to the lifetime of the map
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut (*(*self.map.pool.slot(self.index)).entry.as_mut_ptr()).1 }
+    }
+
+    /// Replaces the value of the entry with `value` and returns the old value
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the entry from the map and returns its value
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Removes the entry from the map and returns its key and value
+    pub fn remove_entry(self) -> (K, V) {
+        unsafe { self.map.unlink(self.bucket, self.prev, self.index) }
+    }
+}
+
+/// An entry of a `HashMap` that contains no value
+pub struct VacantEntry<'a, K, P, S>
+where
+    P: Singleton,
+{
+    bucket: u8,
+    key: K,
+    map: &'a mut HashMap<P, S>,
+}
+
+impl<'a, K, V, N, O, P, S> VacantEntry<'a, K, P, S>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::DerefMut<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    O: PoolObserver,
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns a reference to the key of the entry
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes back the key of the entry
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts `value` into the entry and returns a mutable reference to it
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing the key and `value` is returned
+    pub fn insert(self, value: V) -> Result<&'a mut V, AllocError<(K, V)>> {
+        let index = self.map.link(self.bucket, self.key, value)?;
+
+        unsafe { Ok(&mut (*(*self.map.pool.slot(index)).entry.as_mut_ptr()).1) }
+    }
+}
+
+/// Iterator over the entries of a `HashMap`
+pub struct Iter<'a, P>
+where
+    P: Singleton,
+{
+    _map: PhantomData<&'a P>,
+    bucket: u8,
+    index: u8,
+    len: usize,
+}
+
+impl<'a, K, V, N, O, P> Iterator for Iter<'a, P>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    K: 'a,
+    V: 'a,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        unsafe {
+            next::<_, _, _, _, P>(&mut self.bucket, &mut self.index, &mut self.len)
+                .map(|p| (&(*p).0, &(*p).1))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+/// Iterator over the entries of a `HashMap` that allows modifying the values
+pub struct IterMut<'a, P>
+where
+    P: Singleton,
+{
+    _map: PhantomData<&'a mut P>,
+    bucket: u8,
+    index: u8,
+    len: usize,
+}
+
+impl<'a, K, V, N, O, P> Iterator for IterMut<'a, P>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    K: 'a,
+    V: 'a,
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        unsafe {
+            next::<_, _, _, _, P>(&mut self.bucket, &mut self.index, &mut self.len)
+                .map(|p| (&(*p).0, &mut (*p).1))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+// NOTE `len` stops the iterator before it scans the buckets that follow the last entry
+unsafe fn next<K, V, N, O, P>(
+    bucket: &mut u8,
+    index: &mut u8,
+    len: &mut usize,
+) -> Option<*mut (K, V)>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+{
+    if *len == 0 {
+        return None;
+    }
+
+    while *index == NIL {
+        *index = (*Pool::slot_of::<P>(*bucket)).head;
+        *bucket += 1;
+    }
+
+    let node = &mut *Pool::slot_of::<P>(*index);
+    *index = node.next;
+    *len -= 1;
+
+    Some(node.entry.as_mut_ptr())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        hash::{BuildHasherDefault, Hasher},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use std::{collections::hash_map::RandomState, vec::Vec};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Entry, HashMap, Node};
+    use crate::{error::Cause, nightly::pool::Pool};
+
+    // sends every key to the same bucket
+    #[derive(Default)]
+    struct Collide;
+
+    impl Hasher for Collide {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Node<i32, i32>, U4> = Pool::new();
+
+        let mut map: HashMap<P, RandomState> = HashMap::new(unsafe { P::new() });
+        assert!(map.is_empty());
+
+        for i in 0..4 {
+            assert_eq!(map.insert(i, i * 10).unwrap(), None);
+        }
+        assert_eq!(map.insert(0, -1).unwrap(), Some(0));
+        assert_eq!(map.len(), 4);
+
+        let e = map.insert(4, 40).unwrap_err();
+        assert_eq!(e.cause(), Cause::Exhausted);
+        assert_eq!(e.into_inner(), (4, 40));
+
+        *map.get_mut(&1).unwrap() += 1;
+        assert_eq!(map.get(&1), Some(&11));
+        assert!(!map.contains_key(&4));
+
+        for (_, v) in map.iter_mut() {
+            *v *= 2;
+        }
+        let mut entries = map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, [(0, -2), (1, 22), (2, 40), (3, 60)]);
+
+        assert_eq!(map.remove(&2), Some(40));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.insert(4, 40).unwrap(), None);
+        assert_eq!(map.get(&4), Some(&40));
+    }
+
+    #[test]
+    fn chaining() {
+        #[Singleton]
+        static mut P: Pool<Node<u8, u8>, U4> = Pool::new();
+
+        let mut map: HashMap<P, BuildHasherDefault<Collide>> = HashMap::new(unsafe { P::new() });
+
+        for i in 0..4 {
+            map.insert(i, i).unwrap();
+        }
+
+        // remove from the middle, the front and the back of the chain
+        assert_eq!(map.remove(&1), Some(1));
+        assert_eq!(map.remove(&3), Some(3));
+        assert_eq!(map.remove(&0), Some(0));
+        assert_eq!(map.get(&2), Some(&2));
+        assert_eq!(map.iter().count(), 1);
+
+        match map.entry(5) {
+            Entry::Vacant(entry) => {
+                *entry.insert(50).unwrap() += 5;
+            }
+            Entry::Occupied(_) => unreachable!(),
+        }
+
+        *map.entry(2).and_modify(|v| *v += 1).or_insert(0).unwrap() += 1;
+        assert_eq!(map.get(&2), Some(&4));
+        assert_eq!(map.get(&5), Some(&55));
+
+        match map.entry(2) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), (2, 4)),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn destructor() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A;
+
+        impl A {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Node<u8, A>, U4> = Pool::new();
+
+        let mut map: HashMap<P, RandomState> = HashMap::new(unsafe { P::new() });
+
+        for i in 0..4 {
+            map.insert(i, A::new()).ok().unwrap();
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+
+        // replacing a value drops the old one
+        drop(map.insert(0, A::new()));
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+
+        // giving the pool back drops the remaining values
+        let pool = map.into_pool();
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.list.free, 4);
+    }
+}
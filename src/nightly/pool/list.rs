@@ -0,0 +1,831 @@
+//! Doubly linked list whose nodes are allocated on a memory pool
+
+use core::{fmt, marker::PhantomData, ops, ptr};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Pool, NIL};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A node of a `List`
+///
+/// This is the element type of the memory pool that backs a `List` (or a `Deque`)
+pub struct Node<T> {
+    next: u8,
+    prev: u8,
+    value: T,
+}
+
+/// A handle to an element of a `List`
+///
+/// Handles are slot indices: they are only meaningful to the list that returned them and only
+/// while their element is in that list.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Handle {
+    index: u8,
+}
+
+impl Handle {
+    /// Returns the index of the slot that holds the element
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
+/// A doubly linked list whose nodes are allocated on the memory pool `P`
+///
+/// - The nodes are linked through their slot indices so a node adds two bytes to its element
+/// - Several lists can share the same pool; the pool is passed to the methods that allocate or
+///   free nodes
+/// - Pushing returns a `Handle` that can later be used to access, or remove, the element in O(1)
+/// - `List` must be explicitly freed (`List::free`) or the nodes it holds will be leaked
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{list::{List, Node}, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Node<u32>, U8> = Pool::new();
+///
+/// let mut pool = unsafe { P::new() };
+///
+/// let mut ready: List<P> = List::new();
+/// let mut blocked: List<P> = List::new();
+///
+/// ready.push_back(&mut pool, 1).unwrap();
+/// let task = ready.push_back(&mut pool, 2).unwrap();
+/// ready.push_back(&mut pool, 3).unwrap();
+///
+/// // move a task from the middle of one list to the back of the other
+/// let value = unsafe { ready.remove(&mut pool, task) };
+/// blocked.push_back(&mut pool, value).unwrap();
+///
+/// assert!(ready.iter().eq(&[1, 3]));
+/// assert_eq!(blocked.front(), Some(&2));
+///
+/// // return the nodes to the pool or the memory will be leaked
+/// ready.free(&mut pool);
+/// blocked.free(&mut pool);
+/// ```
+pub struct List<P>
+where
+    P: Singleton,
+{
+    _not_send_or_sync: PhantomData<*const ()>,
+    _pool: PhantomData<P>,
+    head: u8,
+    len: usize,
+    tail: u8,
+}
+
+impl<T, N, O, P> List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>> + ops::DerefMut<Target = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    O: PoolObserver,
+{
+    /// Creates an empty list; this doesn't allocate
+    pub fn new() -> Self {
+        List {
+            _not_send_or_sync: PhantomData,
+            _pool: PhantomData,
+            head: NIL,
+            len: 0,
+            tail: NIL,
+        }
+    }
+
+    /// Prepends `value` to the list and returns a handle to it
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn push_front(&mut self, pool: &mut P, value: T) -> Result<Handle, AllocError<T>> {
+        let next = self.head;
+        self.link(pool, value, NIL, next)
+    }
+
+    /// Appends `value` to the list and returns a handle to it
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn push_back(&mut self, pool: &mut P, value: T) -> Result<Handle, AllocError<T>> {
+        let prev = self.tail;
+        self.link(pool, value, prev, NIL)
+    }
+
+    /// Removes the first element of the list and returns it, or `None` if it's empty
+    pub fn pop_front(&mut self, pool: &mut P) -> Option<T> {
+        if self.head == NIL {
+            None
+        } else {
+            let head = self.head;
+            Some(unsafe { self.unlink(pool, head) })
+        }
+    }
+
+    /// Removes the last element of the list and returns it, or `None` if it's empty
+    pub fn pop_back(&mut self, pool: &mut P) -> Option<T> {
+        if self.tail == NIL {
+            None
+        } else {
+            let tail = self.tail;
+            Some(unsafe { self.unlink(pool, tail) })
+        }
+    }
+
+    /// Removes the element `handle` refers to from the list and returns it
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by this list and its element must still be in the list
+    pub unsafe fn remove(&mut self, pool: &mut P, handle: Handle) -> T {
+        self.unlink(pool, handle.index)
+    }
+
+    /// Removes all the elements of the list and returns its nodes to the `pool`
+    pub fn clear(&mut self, pool: &mut P) {
+        while self.pop_back(pool).is_some() {}
+    }
+
+    /// Drops all the elements of the list and returns its nodes to the `pool`
+    pub fn free(mut self, pool: &mut P) {
+        self.clear(pool)
+    }
+
+    /// Returns a cursor that can edit the list, pointing at its first element
+    pub fn cursor_front_mut<'a>(&'a mut self, pool: &'a mut P) -> CursorMut<'a, P> {
+        let current = self.head;
+        CursorMut {
+            current,
+            list: self,
+            pool,
+        }
+    }
+
+    /// Returns a cursor that can edit the list, pointing at its last element
+    pub fn cursor_back_mut<'a>(&'a mut self, pool: &'a mut P) -> CursorMut<'a, P> {
+        let current = self.tail;
+        CursorMut {
+            current,
+            list: self,
+            pool,
+        }
+    }
+
+    /// Returns a cursor that can edit the list, pointing at the element `handle` refers to
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by this list and its element must still be in the list
+    pub unsafe fn cursor_mut<'a>(
+        &'a mut self,
+        pool: &'a mut P,
+        handle: Handle,
+    ) -> CursorMut<'a, P> {
+        CursorMut {
+            current: handle.index,
+            list: self,
+            pool,
+        }
+    }
+
+    // Allocates a node for `value` and links it between `prev` and `next`, which must be adjacent
+    // (`NIL` stands for the ends of the list)
+    fn link(
+        &mut self,
+        pool: &mut P,
+        value: T,
+        prev: u8,
+        next: u8,
+    ) -> Result<Handle, AllocError<T>> {
+        unsafe {
+            let index = match pool.pop() {
+                Some(index) => index,
+                None => return Err(AllocError::exhausted(value, N::USIZE, N::USIZE)),
+            };
+
+            ptr::write(pool.slot(index), Node { next, prev, value });
+
+            if prev == NIL {
+                self.head = index;
+            } else {
+                (*Pool::slot_of::<P>(prev)).next = index;
+            }
+
+            if next == NIL {
+                self.tail = index;
+            } else {
+                (*Pool::slot_of::<P>(next)).prev = index;
+            }
+
+            self.len += 1;
+
+            Ok(Handle { index })
+        }
+    }
+
+    // Unlinks the node `index` from the list and returns its slot to the `pool`
+    unsafe fn unlink(&mut self, pool: &mut P, index: u8) -> T {
+        let Node { next, prev, value } = ptr::read(pool.slot(index));
+        pool.push(index);
+
+        if prev == NIL {
+            self.head = next;
+        } else {
+            (*Pool::slot_of::<P>(prev)).next = next;
+        }
+
+        if next == NIL {
+            self.tail = prev;
+        } else {
+            (*Pool::slot_of::<P>(next)).prev = prev;
+        }
+
+        self.len -= 1;
+
+        value
+    }
+}
+
+impl<T, N, O, P> List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+{
+    /// Returns the number of elements in the list
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the list contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a reference to the first element of the list, or `None` if it's empty
+    pub fn front(&self) -> Option<&T> {
+        value::<_, _, _, P>(self.head).map(|p| unsafe { &*p })
+    }
+
+    /// Returns a mutable reference to the first element of the list, or `None` if it's empty
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        value::<_, _, _, P>(self.head).map(|p| unsafe { &mut *p })
+    }
+
+    /// Returns a reference to the last element of the list, or `None` if it's empty
+    pub fn back(&self) -> Option<&T> {
+        value::<_, _, _, P>(self.tail).map(|p| unsafe { &*p })
+    }
+
+    /// Returns a mutable reference to the last element of the list, or `None` if it's empty
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        value::<_, _, _, P>(self.tail).map(|p| unsafe { &mut *p })
+    }
+
+    /// Returns a reference to the element `handle` refers to
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by this list and its element must still be in the list
+    pub unsafe fn get(&self, handle: Handle) -> &T {
+        &(*Pool::slot_of::<P>(handle.index)).value
+    }
+
+    /// Returns a mutable reference to the element `handle` refers to
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by this list and its element must still be in the list
+    pub unsafe fn get_mut(&mut self, handle: Handle) -> &mut T {
+        &mut (*Pool::slot_of::<P>(handle.index)).value
+    }
+
+    /// Returns a cursor pointing at the first element of the list
+    pub fn cursor_front(&self) -> Cursor<'_, P> {
+        Cursor {
+            current: self.head,
+            list: self,
+        }
+    }
+
+    /// Returns a cursor pointing at the last element of the list
+    pub fn cursor_back(&self) -> Cursor<'_, P> {
+        Cursor {
+            current: self.tail,
+            list: self,
+        }
+    }
+
+    /// Returns an iterator over the elements of the list
+    pub fn iter(&self) -> Iter<'_, P> {
+        Iter {
+            _list: PhantomData,
+            back: self.tail,
+            front: self.head,
+            len: self.len,
+        }
+    }
+
+    /// Returns an iterator that allows modifying the elements of the list
+    pub fn iter_mut(&mut self) -> IterMut<'_, P> {
+        IterMut {
+            _list: PhantomData,
+            back: self.tail,
+            front: self.head,
+            len: self.len,
+        }
+    }
+}
+
+impl<T, N, O, P> Default for List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>> + ops::DerefMut<Target = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    O: PoolObserver,
+{
+    fn default() -> Self {
+        List::new()
+    }
+}
+
+impl<T, N, O, P> fmt::Debug for List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, N, O, P> IntoIterator for &'a List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, P>;
+
+    fn into_iter(self) -> Iter<'a, P> {
+        self.iter()
+    }
+}
+
+impl<'a, T, N, O, P> IntoIterator for &'a mut List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, P>;
+
+    fn into_iter(self) -> IterMut<'a, P> {
+        self.iter_mut()
+    }
+}
+
+unsafe impl<T, N, O, P> Send for List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: Send,
+{
+}
+
+unsafe impl<T, N, O, P> Sync for List<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: Sync,
+{
+}
+
+/// A cursor over a `List`
+///
+/// The cursor points either at an element or at a "ghost" position between the last and the
+/// first elements of the list.
+pub struct Cursor<'a, P>
+where
+    P: Singleton,
+{
+    current: u8,
+    list: &'a List<P>,
+}
+
+impl<'a, T, N, O, P> Cursor<'a, P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    /// Moves the cursor to the next element; from the last element it moves to the ghost position
+    /// and from the ghost position it moves to the first element
+    pub fn move_next(&mut self) {
+        self.current = if self.current == NIL {
+            self.list.head
+        } else {
+            unsafe { (*Pool::slot_of::<P>(self.current)).next }
+        };
+    }
+
+    /// Moves the cursor to the previous element; from the first element it moves to the ghost
+    /// position and from the ghost position it moves to the last element
+    pub fn move_prev(&mut self) {
+        self.current = if self.current == NIL {
+            self.list.tail
+        } else {
+            unsafe { (*Pool::slot_of::<P>(self.current)).prev }
+        };
+    }
+
+    /// Returns a reference to the element the cursor points at, or `None` at the ghost position
+    pub fn current(&self) -> Option<&'a T> {
+        value::<_, _, _, P>(self.current).map(|p| unsafe { &*p })
+    }
+
+    /// Returns a handle to the element the cursor points at, or `None` at the ghost position
+    pub fn handle(&self) -> Option<Handle> {
+        handle(self.current)
+    }
+}
+
+/// A cursor over a `List` that can insert and remove elements
+///
+/// The cursor points either at an element or at a "ghost" position between the last and the
+/// first elements of the list.
+pub struct CursorMut<'a, P>
+where
+    P: Singleton,
+{
+    current: u8,
+    list: &'a mut List<P>,
+    pool: &'a mut P,
+}
+
+impl<'a, T, N, O, P> CursorMut<'a, P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>> + ops::DerefMut<Target = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    O: PoolObserver,
+{
+    /// Moves the cursor to the next element; from the last element it moves to the ghost position
+    /// and from the ghost position it moves to the first element
+    pub fn move_next(&mut self) {
+        self.current = if self.current == NIL {
+            self.list.head
+        } else {
+            unsafe { (*Pool::slot_of::<P>(self.current)).next }
+        };
+    }
+
+    /// Moves the cursor to the previous element; from the first element it moves to the ghost
+    /// position and from the ghost position it moves to the last element
+    pub fn move_prev(&mut self) {
+        self.current = if self.current == NIL {
+            self.list.tail
+        } else {
+            unsafe { (*Pool::slot_of::<P>(self.current)).prev }
+        };
+    }
+
+    /// Returns a mutable reference to the element the cursor points at, or `None` at the ghost
+    /// position
+    pub fn current(&mut self) -> Option<&mut T> {
+        value::<_, _, _, P>(self.current).map(|p| unsafe { &mut *p })
+    }
+
+    /// Returns a handle to the element the cursor points at, or `None` at the ghost position
+    pub fn handle(&self) -> Option<Handle> {
+        handle(self.current)
+    }
+
+    /// Inserts `value` before the element the cursor points at; at the ghost position `value` is
+    /// appended to the list
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn insert_before(&mut self, value: T) -> Result<Handle, AllocError<T>> {
+        let next = self.current;
+        let prev = if next == NIL {
+            self.list.tail
+        } else {
+            unsafe { (*Pool::slot_of::<P>(next)).prev }
+        };
+
+        self.list.link(self.pool, value, prev, next)
+    }
+
+    /// Inserts `value` after the element the cursor points at; at the ghost position `value` is
+    /// prepended to the list
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn insert_after(&mut self, value: T) -> Result<Handle, AllocError<T>> {
+        let prev = self.current;
+        let next = if prev == NIL {
+            self.list.head
+        } else {
+            unsafe { (*Pool::slot_of::<P>(prev)).next }
+        };
+
+        self.list.link(self.pool, value, prev, next)
+    }
+
+    /// Removes the element the cursor points at and returns it, or `None` at the ghost position
+    ///
+    /// The cursor moves to the element that followed the removed one
+    pub fn remove_current(&mut self) -> Option<T> {
+        if self.current == NIL {
+            return None;
+        }
+
+        unsafe {
+            let index = self.current;
+            self.current = (*Pool::slot_of::<P>(index)).next;
+
+            Some(self.list.unlink(self.pool, index))
+        }
+    }
+}
+
+/// Immutable `List` iterator
+pub struct Iter<'a, P>
+where
+    P: Singleton,
+{
+    _list: PhantomData<&'a List<P>>,
+    back: u8,
+    front: u8,
+    len: usize,
+}
+
+impl<'a, T, N, O, P> Iterator for Iter<'a, P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        unsafe { next::<_, _, _, P>(&mut self.front, &mut self.len).map(|p| &*p) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, N, O, P> DoubleEndedIterator for Iter<'a, P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    fn next_back(&mut self) -> Option<&'a T> {
+        unsafe { next_back::<_, _, _, P>(&mut self.back, &mut self.len).map(|p| &*p) }
+    }
+}
+
+/// Mutable `List` iterator
+pub struct IterMut<'a, P>
+where
+    P: Singleton,
+{
+    _list: PhantomData<&'a mut List<P>>,
+    back: u8,
+    front: u8,
+    len: usize,
+}
+
+impl<'a, T, N, O, P> Iterator for IterMut<'a, P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        unsafe { next::<_, _, _, P>(&mut self.front, &mut self.len).map(|p| &mut *p) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T, N, O, P> DoubleEndedIterator for IterMut<'a, P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    T: 'a,
+{
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        unsafe { next_back::<_, _, _, P>(&mut self.back, &mut self.len).map(|p| &mut *p) }
+    }
+}
+
+// NOTE `len` stops the iterator when the two ends meet
+unsafe fn next<T, N, O, P>(front: &mut u8, len: &mut usize) -> Option<*mut T>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+{
+    if *len == 0 {
+        return None;
+    }
+
+    let node = &mut *Pool::slot_of::<P>(*front);
+    *front = node.next;
+    *len -= 1;
+
+    Some(&mut node.value)
+}
+
+unsafe fn next_back<T, N, O, P>(back: &mut u8, len: &mut usize) -> Option<*mut T>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+{
+    if *len == 0 {
+        return None;
+    }
+
+    let node = &mut *Pool::slot_of::<P>(*back);
+    *back = node.prev;
+    *len -= 1;
+
+    Some(&mut node.value)
+}
+
+fn value<T, N, O, P>(index: u8) -> Option<*mut T>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+{
+    if index == NIL {
+        None
+    } else {
+        unsafe { Some(&mut (*Pool::slot_of::<P>(index)).value) }
+    }
+}
+
+fn handle(index: u8) -> Option<Handle> {
+    if index == NIL {
+        None
+    } else {
+        Some(Handle { index })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{List, Node};
+    use crate::{error::Cause, nightly::pool::Pool};
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Node<i32>, U4> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut xs: List<P> = List::new();
+        assert!(xs.is_empty());
+        assert_eq!(xs.pop_front(pool), None);
+        assert_eq!(xs.front(), None);
+
+        let b = xs.push_back(pool, 1).unwrap();
+        xs.push_back(pool, 2).unwrap();
+        xs.push_front(pool, 0).unwrap();
+
+        let mut ys: List<P> = List::new();
+        ys.push_back(pool, 3).unwrap();
+        let e = xs.push_back(pool, 4).err().unwrap();
+        assert_eq!((e.cause(), e.into_inner()), (Cause::Exhausted, 4));
+
+        assert_eq!(xs.len(), 3);
+        assert!(xs.iter().eq(&[0, 1, 2]));
+        assert!(xs.iter().rev().eq(&[2, 1, 0]));
+
+        for x in xs.iter_mut() {
+            *x *= 10;
+        }
+        assert_eq!(unsafe { *xs.get(b) }, 10);
+
+        // removing by handle frees the node
+        assert_eq!(unsafe { xs.remove(pool, b) }, 10);
+        assert_eq!(pool.list.free, 1);
+        assert!(xs.iter().eq(&[0, 20]));
+
+        assert_eq!(xs.pop_back(pool), Some(20));
+        assert_eq!(xs.pop_front(pool), Some(0));
+        assert_eq!(xs.pop_front(pool), None);
+        assert_eq!(xs.back(), None);
+        assert_eq!(pool.list.free, 3);
+
+        ys.free(pool);
+        assert_eq!(pool.list.free, 4);
+    }
+
+    #[test]
+    fn cursor() {
+        #[Singleton]
+        static mut P: Pool<Node<i32>, U8> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut xs: List<P> = List::new();
+        for i in 0..5 {
+            xs.push_back(pool, i).unwrap();
+        }
+
+        let mut cursor = xs.cursor_front_mut(pool);
+        while let Some(x) = cursor.current() {
+            if *x % 2 == 0 {
+                cursor.remove_current();
+            } else {
+                let x = *x;
+                cursor.insert_before(-x).unwrap();
+                cursor.move_next();
+            }
+        }
+
+        // at the ghost position
+        assert_eq!(cursor.handle(), None);
+        cursor.insert_before(5).unwrap();
+        cursor.insert_after(-5).unwrap();
+
+        assert!(xs.iter().eq(&[-5, -1, 1, -3, 3, 5]));
+
+        let mut cursor = xs.cursor_back();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&3));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&-5));
+
+        xs.free(pool);
+        assert_eq!(pool.list.free, 8);
+    }
+
+    #[test]
+    fn destructor() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A;
+
+        impl A {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Node<A>, U4> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut xs: List<P> = List::new();
+        for _ in 0..4 {
+            xs.push_back(pool, A::new()).ok().unwrap();
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+
+        drop(xs.pop_front(pool));
+        assert_eq!(COUNT.load(Ordering::SeqCst), 3);
+
+        // Freeing the `List` should drop all its elements
+        List::free(xs, pool);
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.list.free, 4);
+    }
+}
@@ -0,0 +1,195 @@
+// Forwards the standard traits from the `Box` of a pool module to the value it points to
+//
+// `$bounds` are the bounds that tie `P` to a pool of `T`s; they must make `Box<P>` implement
+// `DerefMut<Target = T>`. `Deref`, `DerefMut`, `Send`, `Sync` and `Unpin` are not forwarded because
+// each pool implements them differently.
+macro_rules! forward_box_traits {
+    ($($bounds:tt)*) => {
+        unsafe impl<T, N, O, P> stable_deref_trait::StableDeref for Box<P>
+        where
+            $($bounds)*
+        {
+        }
+
+        impl<T, N, O, P> core::fmt::Debug for Box<P>
+        where
+            $($bounds)*,
+            T: core::fmt::Debug,
+        {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                T::fmt(&**self, f)
+            }
+        }
+
+        impl<T, N, O, P> core::fmt::Display for Box<P>
+        where
+            $($bounds)*,
+            T: core::fmt::Display,
+        {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                T::fmt(&**self, f)
+            }
+        }
+
+        impl<T, N, O, P> core::fmt::Pointer for Box<P>
+        where
+            $($bounds)*
+        {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                let ptr: *const T = &**self;
+                core::fmt::Pointer::fmt(&ptr, f)
+            }
+        }
+
+        impl<T, N, O, P> PartialEq for Box<P>
+        where
+            $($bounds)*,
+            T: PartialEq,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                T::eq(&**self, &**other)
+            }
+        }
+
+        impl<T, N, O, P> Eq for Box<P>
+        where
+            $($bounds)*,
+            T: Eq,
+        {
+        }
+
+        impl<T, N, O, P> PartialOrd for Box<P>
+        where
+            $($bounds)*,
+            T: PartialOrd,
+        {
+            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                T::partial_cmp(&**self, &**other)
+            }
+        }
+
+        impl<T, N, O, P> Ord for Box<P>
+        where
+            $($bounds)*,
+            T: Ord,
+        {
+            fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+                T::cmp(&**self, &**other)
+            }
+        }
+
+        impl<T, N, O, P> core::hash::Hash for Box<P>
+        where
+            $($bounds)*,
+            T: core::hash::Hash,
+        {
+            fn hash<H>(&self, state: &mut H)
+            where
+                H: core::hash::Hasher,
+            {
+                T::hash(&**self, state)
+            }
+        }
+
+        impl<T, N, O, P> AsRef<T> for Box<P>
+        where
+            $($bounds)*
+        {
+            fn as_ref(&self) -> &T {
+                self
+            }
+        }
+
+        impl<T, N, O, P> AsMut<T> for Box<P>
+        where
+            $($bounds)*
+        {
+            fn as_mut(&mut self) -> &mut T {
+                self
+            }
+        }
+
+        impl<T, N, O, P> Iterator for Box<P>
+        where
+            $($bounds)*,
+            T: Iterator,
+        {
+            type Item = T::Item;
+
+            fn next(&mut self) -> Option<T::Item> {
+                T::next(&mut **self)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                T::size_hint(&**self)
+            }
+        }
+
+        impl<T, N, O, P> DoubleEndedIterator for Box<P>
+        where
+            $($bounds)*,
+            T: DoubleEndedIterator,
+        {
+            fn next_back(&mut self) -> Option<T::Item> {
+                T::next_back(&mut **self)
+            }
+        }
+
+        impl<T, N, O, P> ExactSizeIterator for Box<P>
+        where
+            $($bounds)*,
+            T: ExactSizeIterator,
+        {
+        }
+
+        impl<T, N, O, P> core::future::Future for Box<P>
+        where
+            $($bounds)*,
+            T: core::future::Future + Unpin,
+        {
+            type Output = T::Output;
+
+            fn poll(
+                mut self: core::pin::Pin<&mut Self>,
+                cx: &mut core::task::Context,
+            ) -> core::task::Poll<T::Output> {
+                T::poll(core::pin::Pin::new(&mut **self), cx)
+            }
+        }
+
+        impl<T, N, O, P> core::fmt::Write for Box<P>
+        where
+            $($bounds)*,
+            T: core::fmt::Write,
+        {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                T::write_str(&mut **self, s)
+            }
+        }
+
+        #[cfg(feature = "defmt")]
+        impl<T, N, O, P> defmt::Format for Box<P>
+        where
+            $($bounds)*,
+            T: defmt::Format,
+        {
+            fn format(&self, f: defmt::Formatter) {
+                T::format(self, f)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<T, N, O, P> serde::Serialize for Box<P>
+        where
+            $($bounds)*,
+            T: serde::Serialize,
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                T::serialize(self, serializer)
+            }
+        }
+    };
+}
@@ -0,0 +1,1132 @@
+//! Per-core memory pool whose values can be freed from any core
+
+use core::{
+    cell::Cell,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops,
+    pin::Pin,
+    ptr,
+};
+
+#[cfg(not(loom))]
+use generic_array::GenericArray;
+use generic_array::{
+    typenum::{consts::U256, IsLess, True},
+    ArrayLength,
+};
+use owned_singleton::Singleton;
+
+use super::{FreeList, Links, NIL};
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+    stats::PoolStats,
+    sync::{AtomicUsize, Ordering, UnsafeCell},
+};
+
+/// A value allocated on the memory pool `P`
+///
+/// - `Box` can be sent to, and dropped on, any core
+/// - Dropping a `Box` returns its memory to the pool through the pool's "remote free" list
+/// - `Box::free` is a faster way to return the memory to the pool that can only be used on the core
+///   that owns the pool
+/// - `sizeof(Box<_>)` is a single byte
+/// - `Box<P>` implements `Send` if it derefs to a type `T` that implements `Send`
+/// - `Box<P>` implements `Sync` if it derefs to a type `T` that implements `Sync`
+pub struct Box<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    _not_send_or_sync: PhantomData<*const ()>,
+    _pool: PhantomData<P>,
+    index: u8,
+}
+
+impl<T, N, O, P> Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Pins the value in place
+    ///
+    /// This is free: pool slots never move and the value is dropped in place when the `Box` is
+    /// freed
+    pub fn into_pin(b: Self) -> Pin<Self> {
+        unsafe { Pin::new_unchecked(b) }
+    }
+}
+
+impl<T, N, O, P> Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Allocates the given `value` on the pool
+    ///
+    /// Memory freed by other cores since the last allocation is reclaimed first
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn new(pool: &P, value: T) -> Result<Box<P>, AllocError<T>> {
+        unsafe {
+            assert!(mem::size_of::<T>() > 0);
+
+            if let Some(index) = pool.pop() {
+                pool.with_slot_mut(index, |slot| ptr::write(slot, value));
+
+                Ok(Box {
+                    _not_send_or_sync: PhantomData,
+                    _pool: PhantomData,
+                    index,
+                })
+            } else {
+                Err(AllocError::exhausted(value, N::USIZE, N::USIZE))
+            }
+        }
+    }
+
+    /// Allocates the given `value` on the pool and pins it in place
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn pin(pool: &P, value: T) -> Result<Pin<Box<P>>, AllocError<T>> {
+        Box::new(pool, value).map(Box::into_pin)
+    }
+
+    /// Returns a `serde` seed that deserializes a value straight into the `pool`
+    ///
+    /// Deserialization fails if the pool has been exhausted
+    #[cfg(feature = "serde")]
+    pub fn seed(pool: &P) -> BoxSeed<'_, P> {
+        BoxSeed { pool }
+    }
+
+    /// Returns this `Box` to the local free list of the `pool`
+    ///
+    /// This is cheaper than dropping the `Box` but requires access to the `pool`, i.e. this can
+    /// only be done on the core that owns the pool
+    ///
+    /// *NOTE*: This method must be invoked as `Box::free(x, pool)`, `x.free(pool)` doesn't compile.
+    pub fn free(self, pool: &P) {
+        unsafe {
+            pool.with_slot_mut(self.index, |slot| ptr::drop_in_place(slot));
+
+            pool.push(self.index);
+        }
+
+        mem::forget(self)
+    }
+
+    /// Moves the value into the pool `dst` and returns its slot to its current pool
+    ///
+    /// Like dropping the `Box`, this can be done on any core: the slot goes to the "remote free"
+    /// list of its current pool. `dst` must be owned by the calling core.
+    ///
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing this `Box` is returned
+    pub fn transfer<M, R, Q>(self, dst: &Q) -> Result<Box<Q>, Box<P>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::Deref<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+    {
+        unsafe {
+            if let Some(index) = dst.pop() {
+                let src = &*P::get();
+
+                src.with_slot(self.index, |from| {
+                    dst.with_slot_mut(index, |to| ptr::copy_nonoverlapping(from, to, 1))
+                });
+                O::on_free(self.index);
+                src.splice_remote(self.index, self.index);
+                mem::forget(self);
+
+                Ok(Box {
+                    _not_send_or_sync: PhantomData,
+                    _pool: PhantomData,
+                    index,
+                })
+            } else {
+                Err(self)
+            }
+        }
+    }
+
+    /// Allocates a clone of the value on the pool `dst`
+    ///
+    /// `dst` must be owned by the calling core
+    ///
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing the clone is returned
+    pub fn try_clone_in<M, R, Q>(&self, dst: &Q) -> Result<Box<Q>, AllocError<T>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::Deref<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+        T: Clone,
+    {
+        Box::new(dst, T::clone(self))
+    }
+
+    /// Takes `n` slots off the `pool` in one go
+    ///
+    /// The slots are turned into `Box`es with `BoxBatch::alloc`.
+    ///
+    /// # Errors
+    ///
+    /// If the pool has less than `n` free slots no slot is taken and an error containing `n` is
+    /// returned; the error reports how many slots were in use
+    pub fn alloc_many(pool: &P, n: u8) -> Result<BoxBatch<P>, AllocError<u8>> {
+        Pool::reserve(pool, n).map_err(|free| {
+            O::on_exhausted();
+
+            AllocError::exhausted(n, N::USIZE - usize::from(free), N::USIZE)
+        })
+    }
+
+    /// Returns all the `boxes` to the local free list of the `pool` in one go
+    ///
+    /// Like `Box::free` this can only be done on the core that owns the pool
+    pub fn free_many<I>(pool: &P, boxes: I)
+    where
+        I: IntoIterator<Item = Box<P>>,
+    {
+        unsafe {
+            if let Some((first, last, count)) = link(&**pool, boxes) {
+                pool.splice(first, last, count)
+            }
+        }
+    }
+
+    /// Drops all the `boxes` and returns their memory to the pool's "remote free" list in one go
+    ///
+    /// This can be done on any core and performs a single successful CAS operation regardless of the
+    /// number of `boxes`
+    pub fn drop_many<I>(boxes: I)
+    where
+        I: IntoIterator<Item = Box<P>>,
+    {
+        unsafe {
+            let pool = &*P::get();
+
+            if let Some((first, last, _)) = link(pool, boxes) {
+                pool.splice_remote(first, last)
+            }
+        }
+    }
+}
+
+// Drops the values of the `boxes` and links their slots together; returns `(first, last, count)`
+unsafe fn link<T, N, O, P, I>(pool: &Pool<T, N, O>, boxes: I) -> Option<(u8, u8, u8)>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    I: IntoIterator<Item = Box<P>>,
+{
+    let mut first = NIL;
+    let mut last = NIL;
+    let mut count = 0;
+
+    for b in boxes {
+        pool.with_slot_mut(b.index, |slot| ptr::drop_in_place(slot));
+        pool.set_next(b.index, first);
+        O::on_free(b.index);
+
+        if last == NIL {
+            last = b.index;
+        }
+        first = b.index;
+        count += 1;
+
+        mem::forget(b);
+    }
+
+    if count == 0 {
+        None
+    } else {
+        Some((first, last, count))
+    }
+}
+
+/// A batch of slots taken off the memory pool `P` in one go (see `Box::alloc_many` and
+/// `Pool::reserve`)
+///
+/// - Like `Box`, `BoxBatch` can be sent to, and dropped on, any core
+/// - Dropping a `BoxBatch` returns the slots it still holds to the pool's "remote free" list
+/// - `BoxBatch<P>` implements `Send` if `P` is a pool of values that implement `Send`
+pub struct BoxBatch<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    _not_send_or_sync: PhantomData<*const ()>,
+    _pool: PhantomData<P>,
+    head: u8,
+    len: u8,
+}
+
+impl<T, N, O, P> BoxBatch<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Moves `value` into one of the slots of the batch
+    ///
+    /// # Panics
+    ///
+    /// This method panics if all the slots of the batch have been used (see `try_alloc`)
+    pub fn alloc(&mut self, value: T) -> Box<P> {
+        assert!(self.len != 0, "all the slots of the batch have been used");
+
+        unsafe {
+            let pool = &*P::get();
+            let index = self.head;
+
+            self.head = pool.next(index);
+            self.len -= 1;
+            pool.reserved.fetch_sub(1, Ordering::Relaxed);
+            pool.with_slot_mut(index, |slot| ptr::write(slot, value));
+            O::on_alloc(index);
+
+            Box {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                index,
+            }
+        }
+    }
+
+    /// Moves `value` into one of the slots of the batch
+    ///
+    /// # Errors
+    ///
+    /// If all the slots of the batch have been used an error containing `value` is returned
+    pub fn try_alloc(&mut self, value: T) -> Result<Box<P>, AllocError<T>> {
+        if self.len == 0 {
+            Err(AllocError::new(value, Cause::ReservationMissing))
+        } else {
+            Ok(self.alloc(value))
+        }
+    }
+
+    /// Returns the number of slots left in the batch
+    pub fn len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns `true` if all the slots of the batch have been used
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<P> Drop for BoxBatch<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    fn drop(&mut self) {
+        use self::sealed::Dealloc;
+
+        if self.len != 0 {
+            unsafe { (*P::get()).dealloc_many(self.head, self.len) }
+        }
+    }
+}
+
+unsafe impl<T, N, O, P> Send for BoxBatch<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: Send,
+{
+}
+
+impl<P> Drop for Box<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    fn drop(&mut self) {
+        use self::sealed::Dealloc;
+
+        unsafe { (*P::get()).dealloc(self.index) }
+    }
+}
+
+impl<T, N, O, P> ops::Deref for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(*P::get()).slot(self.index) }
+    }
+}
+
+impl<T, N, O, P> ops::DerefMut for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(*P::get()).slot(self.index) }
+    }
+}
+
+unsafe impl<T, N, O, P> Send for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: Send,
+{
+}
+
+unsafe impl<T, N, O, P> Sync for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: Sync,
+{
+}
+
+// NOTE moving the `Box` doesn't move the value, which stays in its slot
+impl<P> Unpin for Box<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+}
+
+forward_box_traits!(
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver
+);
+
+/// A fixed-size memory pool owned by a single core
+///
+/// Only the core that holds the handle to the pool can allocate on it; that core has exclusive
+/// access to the (local) free list so allocation requires no atomic operations or critical
+/// sections. `Box`es, on the other hand, can be freed on any core: memory freed on other cores is
+/// pushed onto a lock-free "remote free" list that the owner core reclaims on its next allocation.
+///
+/// Give each core its own pool to get contention-free allocation.
+///
+/// *NOTE*: Freeing memory from other cores requires atomic compare-and-swap (CAS) instructions
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+///
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{consts::*, pool::multicore::{Box, Pool}};
+///
+/// // one pool per core
+/// #[Singleton]
+/// static P0: Pool<[u8; 128], U2> = Pool::new();
+///
+/// // (on core #0)
+/// let pool = unsafe { P0::new() };
+///
+/// let a: Box<P0> = Box::new(&pool, [0; 128]).ok().unwrap();
+/// let b: Box<P0> = Box::new(&pool, [1; 128]).ok().unwrap();
+///
+/// // the memory of a `Box` dropped on another core goes to the remote free list
+/// thread::spawn(move || drop(b)).join().unwrap();
+///
+/// // ... and it's reclaimed by the owner core when it next allocates
+/// let c: Box<P0> = Box::new(&pool, [2; 128]).ok().unwrap();
+///
+/// // the owner core can free memory without atomic operations
+/// Box::free(a, &pool);
+/// ```
+pub struct Pool<T, N, O = ()>
+where
+    N: ArrayLength<T>,
+{
+    #[cfg(loom)]
+    _capacity: PhantomData<N>,
+    _not_send_or_sync: PhantomData<*const ()>,
+    _observer: PhantomData<O>,
+    list: Cell<FreeList>,
+    #[cfg(not(loom))]
+    memory: UnsafeCell<MaybeUninit<GenericArray<T, N>>>,
+    #[cfg(loom)]
+    memory: std::vec::Vec<UnsafeCell<MaybeUninit<T>>>,
+    // head of the "remote free" list; `NIL` if empty
+    remote: AtomicUsize,
+    // slots held by `BoxBatch`es, which can be used and dropped on any core
+    reserved: AtomicUsize,
+}
+
+impl<T, N, O> Pool<T, N, O>
+where
+    N: ArrayLength<T> + IsLess<U256, Output = True>,
+{
+    loom_const_fn! {
+        /// Creates a new memory pool
+        pub fn new() -> Self {
+            Pool {
+                #[cfg(loom)]
+                _capacity: PhantomData,
+                _not_send_or_sync: PhantomData,
+                _observer: PhantomData,
+                list: Cell::new(FreeList::new(N::U8)),
+                #[cfg(not(loom))]
+                memory: UnsafeCell::new(MaybeUninit::uninitialized()),
+                #[cfg(loom)]
+                memory: (0..N::USIZE)
+                    .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                    .collect(),
+                remote: AtomicUsize::new(NIL as usize),
+                reserved: AtomicUsize::new(0),
+            }
+        }
+    }
+}
+
+impl<T, N, O> Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Reserves `k` slots of the `pool` for the exclusive use of the returned `BoxBatch`
+    ///
+    /// The reserved slots are taken off the local free list so other `Box::new` calls can't take
+    /// them and allocating on them (`BoxBatch::alloc`) doesn't need access to the pool. The slots
+    /// that are not used return to the pool when the `BoxBatch` is dropped.
+    ///
+    /// Must be called from the core that owns the pool
+    ///
+    /// # Errors
+    ///
+    /// If the pool has less than `k` free slots nothing is reserved and an error containing the
+    /// number of free slots is returned
+    pub fn reserve<P>(pool: &P, k: u8) -> Result<BoxBatch<P>, u8>
+    where
+        P: Singleton<Type = Self> + ops::Deref<Target = Self>,
+    {
+        unsafe {
+            assert!(mem::size_of::<T>() > 0);
+
+            let head = pool.detach(k)?;
+            pool.reserved.fetch_add(usize::from(k), Ordering::Relaxed);
+
+            Ok(BoxBatch {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                head,
+                len: k,
+            })
+        }
+    }
+
+    /// Returns a snapshot of the usage of the pool
+    ///
+    /// Slots freed from other cores are counted as live until the next allocation reclaims them
+    pub fn stats(&self) -> PoolStats {
+        let reserved = self.reserved.load(Ordering::Relaxed) as u8;
+
+        PoolStats {
+            capacity: N::U8,
+            live: N::U8 - self.list.get().free - reserved,
+            reserved,
+        }
+    }
+
+    // Runs `f` on a pointer to the slot `index`; under loom the access is tracked per slot
+    #[cfg(not(loom))]
+    unsafe fn with_slot<R>(&self, index: u8, f: impl FnOnce(*const T) -> R) -> R {
+        self.memory
+            .with(|memory| f((memory as *const T).add(usize::from(index))))
+    }
+
+    #[cfg(loom)]
+    unsafe fn with_slot<R>(&self, index: u8, f: impl FnOnce(*const T) -> R) -> R {
+        self.memory[usize::from(index)].with(|slot| f(slot as *const T))
+    }
+
+    #[cfg(not(loom))]
+    unsafe fn with_slot_mut<R>(&self, index: u8, f: impl FnOnce(*mut T) -> R) -> R {
+        self.memory
+            .with_mut(|memory| f((memory as *mut T).add(usize::from(index))))
+    }
+
+    #[cfg(loom)]
+    unsafe fn with_slot_mut<R>(&self, index: u8, f: impl FnOnce(*mut T) -> R) -> R {
+        self.memory[usize::from(index)].with_mut(|slot| f(slot as *mut T))
+    }
+
+    // NOTE only for `Deref`: the references it hands out outlive any `with_slot` closure
+    fn slot(&self, index: u8) -> *mut T {
+        unsafe { self.with_slot_mut(index, |slot| slot) }
+    }
+
+    // Takes a slot off the local free list, reclaiming the remote free list first
+    //
+    // Must only be called from the owner core
+    unsafe fn pop(&self) -> Option<u8> {
+        self.reclaim();
+
+        let mut list = self.list.get();
+        let index = list.pop(N::U8, self);
+        self.list.set(list);
+
+        if let Some(index) = index {
+            O::on_alloc(index);
+        } else {
+            O::on_exhausted();
+        }
+
+        index
+    }
+
+    // Puts a slot back on the local free list
+    //
+    // Must only be called from the owner core
+    unsafe fn push(&self, index: u8) {
+        let mut list = self.list.get();
+        list.push(index, self);
+        self.list.set(list);
+
+        O::on_free(index);
+    }
+
+    // Takes `n` slots off the local free list in one go and returns the first one; the slots stay
+    // linked to each other
+    //
+    // Must only be called from the owner core
+    unsafe fn detach(&self, n: u8) -> Result<u8, u8> {
+        self.reclaim();
+
+        let mut list = self.list.get();
+        let first = list.detach(n, self);
+        self.list.set(list);
+
+        first
+    }
+
+    // Puts the `count` slots linked from `first` to `last` back on the local free list in one go
+    //
+    // Must only be called from the owner core
+    unsafe fn splice(&self, first: u8, last: u8, count: u8) {
+        let mut list = self.list.get();
+        list.splice(first, last, count, self);
+        self.list.set(list);
+    }
+
+    // Moves the whole remote free list onto the local free list
+    //
+    // Must only be called from the owner core
+    unsafe fn reclaim(&self) {
+        if self.remote.load(Ordering::Relaxed) == NIL as usize {
+            return;
+        }
+
+        // NOTE taking the whole list, rather than popping nodes one by one, avoids the ABA problem
+        let first = self.remote.swap(NIL as usize, Ordering::Acquire) as u8;
+
+        let mut last = first;
+        let mut count = 1;
+        loop {
+            let next = self.next(last);
+            if next == NIL {
+                break;
+            }
+
+            last = next;
+            count += 1;
+        }
+
+        self.splice(first, last, count);
+    }
+
+    // Pushes the slots linked from `first` to `last` onto the remote free list
+    //
+    // Can be called from any core
+    unsafe fn splice_remote(&self, first: u8, last: u8) {
+        let mut head = self.remote.load(Ordering::Relaxed);
+
+        loop {
+            self.set_next(last, head as u8);
+
+            match self.remote.compare_exchange_weak(
+                head,
+                usize::from(first),
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+    }
+}
+
+// NOTE the links are accessed through `with_slot` so that loom tracks them
+impl<T, N, O> Links for Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    unsafe fn next(&self, index: u8) -> u8 {
+        self.with_slot(index, |slot| *(slot as *const u8))
+    }
+
+    unsafe fn set_next(&self, index: u8, next: u8) {
+        self.with_slot_mut(index, |slot| *(slot as *mut u8) = next)
+    }
+}
+
+unsafe impl<T, N, O> sealed::Dealloc for Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    unsafe fn dealloc(&self, index: u8) {
+        self.with_slot_mut(index, |slot| ptr::drop_in_place(slot));
+        O::on_free(index);
+
+        self.splice_remote(index, index);
+    }
+
+    unsafe fn dealloc_many(&self, first: u8, count: u8) {
+        self.reserved
+            .fetch_sub(usize::from(count), Ordering::Relaxed);
+
+        let mut last = first;
+        for _ in 1..count {
+            last = self.next(last);
+        }
+
+        self.splice_remote(first, last);
+    }
+}
+
+unsafe impl<T, N, O> Send for Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    T: Send,
+{
+}
+
+mod sealed {
+    pub unsafe trait Dealloc {
+        unsafe fn dealloc(&self, index: u8);
+
+        // returns `count` unused slots linked from `first`
+        unsafe fn dealloc_many(&self, first: u8, count: u8);
+    }
+}
+
+/// Deserializes a value into a `Box` allocated on the memory pool `P` (see `Box::seed`)
+#[cfg(feature = "serde")]
+pub struct BoxSeed<'a, P>
+where
+    P: Singleton,
+{
+    pool: &'a P,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a, T, N, O, P> serde::de::DeserializeSeed<'de> for BoxSeed<'a, P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: serde::Deserialize<'de>,
+{
+    type Value = Box<P>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Box<P>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+
+        Box::new(self.pool, value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+#[allow(clippy::just_underscores_and_digits)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Box, Pool};
+    use crate::{error::Cause, nightly::pool::NIL};
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static P: Pool<i8, U4> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        let _0 = Box::new(pool, 0).unwrap();
+        let _1 = Box::new(pool, 1).unwrap();
+        let _2 = Box::new(pool, 2).unwrap();
+        let _3 = Box::new(pool, 3).unwrap();
+        assert!(Box::new(pool, 4).is_err());
+
+        // dropping pushes onto the remote free list
+        drop(_1);
+        drop(_3);
+        assert_eq!(pool.list.get().free, 0);
+        assert_eq!(pool.remote.load(Ordering::Relaxed), 3);
+
+        // which is reclaimed on the next allocation
+        let _3 = Box::new(pool, -3).unwrap();
+        assert_eq!(_3.index, 3);
+        assert_eq!(pool.remote.load(Ordering::Relaxed), NIL as usize);
+        assert_eq!(pool.list.get().free, 1);
+
+        // `free` pushes onto the local free list
+        Box::free(_0, pool);
+        assert_eq!(pool.list.get().head, 0);
+        assert_eq!(pool.list.get().free, 2);
+
+        let _0 = Box::new(pool, 0).unwrap();
+        let _1 = Box::new(pool, -1).unwrap();
+        assert_eq!((_0.index, *_1), (0, -1));
+        assert!(Box::new(pool, 4).is_err());
+    }
+
+    #[test]
+    fn cross_thread() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        #[allow(dead_code)]
+        pub struct A(u32);
+
+        impl A {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A(0)
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static P: Pool<A, U32> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        for _ in 0..100 {
+            let mut xs = vec![];
+            while let Ok(x) = Box::new(pool, A::new()) {
+                xs.push(x);
+            }
+            assert_eq!(COUNT.load(Ordering::SeqCst), 32);
+
+            let ys = xs.split_off(16);
+            let t0 = thread::spawn(move || drop(xs));
+            let t1 = thread::spawn(move || drop(ys));
+            t0.join().unwrap();
+            t1.join().unwrap();
+
+            assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        }
+    }
+
+    #[test]
+    fn transfer() {
+        #[Singleton]
+        static P: Pool<i32, U1> = Pool::new();
+
+        #[Singleton]
+        static Q: Pool<i32, U2> = Pool::new();
+
+        let ref p = unsafe { P::new() };
+        let ref q = unsafe { Q::new() };
+
+        let a = Box::new(p, 0).unwrap();
+        let b = a.try_clone_in(q).unwrap();
+        let c = b.try_clone_in(q).unwrap();
+        assert_eq!(b.try_clone_in(q).err().map(|e| e.into_inner()), Some(0));
+
+        // `dst` is full
+        let a = match a.transfer(q) {
+            Err(a) => a,
+            Ok(_) => unreachable!(),
+        };
+
+        Box::free(c, q);
+
+        // the source slot goes to the remote free list
+        let a = a.transfer(q).ok().unwrap();
+        assert_eq!((*a, a.index), (0, 1));
+        assert_eq!(p.remote.load(Ordering::Relaxed), 0);
+        assert_eq!(q.list.get().free, 0);
+
+        let d = Box::new(p, 1).unwrap();
+        assert_eq!(d.index, 0);
+        drop((a, b, d));
+    }
+
+    #[test]
+    fn batch() {
+        #[Singleton]
+        static P: Pool<i8, U8> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        let e = Box::alloc_many(pool, 9).err().unwrap();
+        assert_eq!(
+            (e.cause(), e.live(), e.into_inner()),
+            (Cause::Exhausted, Some(0), 9)
+        );
+
+        let mut batch = Box::alloc_many(pool, 6).ok().unwrap();
+        let mut xs = vec![];
+        while let Ok(x) = batch.try_alloc(0) {
+            xs.push(x);
+        }
+        assert!(batch.is_empty());
+        assert_eq!(pool.list.get().free, 2);
+
+        // one CAS for the whole list
+        let ys = xs.split_off(3);
+        thread::spawn(move || Box::drop_many(ys)).join().unwrap();
+        assert_eq!(pool.remote.load(Ordering::Relaxed), 5);
+
+        Box::free_many(pool, xs);
+        assert_eq!(pool.list.get().free, 5);
+
+        // unused slots are returned when the batch is dropped
+        let mut batch = Box::alloc_many(pool, 8).ok().unwrap();
+        assert_eq!(pool.list.get().free, 0);
+        let x = batch.alloc(1);
+        let stats = pool.stats();
+        assert_eq!((stats.live, stats.reserved), (1, 7));
+        thread::spawn(move || drop(batch)).join().unwrap();
+        assert_eq!(pool.stats().reserved, 0);
+
+        let _batch = Box::alloc_many(pool, 7).ok().unwrap();
+        assert!(Box::new(pool, 2).is_err());
+        drop(x);
+    }
+}
+
+// Model checked with: `RUSTFLAGS="--cfg loom" cargo test --features nightly --release --lib models`
+#[cfg(all(test, loom))]
+mod models {
+    use core::{ops, ptr};
+
+    use generic_array::{typenum::consts::*, ArrayLength};
+    use loom::{sync::Arc, thread};
+    use owned_singleton::Singleton;
+    use stable_deref_trait::StableDeref;
+
+    use super::{sealed::Dealloc, Box, Pool};
+
+    // NOTE every model yields the owner core right after spawning the remote cores; otherwise loom
+    // runs the owner first and its DPOR misses the schedules where a remote `free` lands in between
+    // the owner's `remote` load and swap
+
+    // the owner core and the remote cores share the pool
+    struct Shared<N>(Pool<u32, N>)
+    where
+        N: ArrayLength<u32>;
+
+    unsafe impl<N> Sync for Shared<N> where N: ArrayLength<u32> {}
+
+    unsafe fn alloc<N>(pool: &Pool<u32, N>, value: u32) -> Option<u8>
+    where
+        N: ArrayLength<u32>,
+    {
+        pool.pop().map(|index| {
+            pool.with_slot_mut(index, |slot| ptr::write(slot, value));
+            index
+        })
+    }
+
+    // Takes all the remaining slots and checks that none is handed out twice
+    unsafe fn drain<N>(pool: &Pool<u32, N>, mut taken: u32) -> u32
+    where
+        N: ArrayLength<u32>,
+    {
+        while let Some(index) = pool.pop() {
+            assert_eq!(taken & (1 << index), 0);
+            taken |= 1 << index;
+        }
+
+        taken
+    }
+
+    #[test]
+    fn remote_frees() {
+        loom::model(|| {
+            let pool = Arc::new(Shared::<U3>(Pool::new()));
+
+            let a = unsafe { alloc(&pool.0, 0).unwrap() };
+            let b = unsafe { alloc(&pool.0, 1).unwrap() };
+
+            // two remote cores free their values while the owner core allocates
+            let threads = [a, b]
+                .iter()
+                .map(|&index| {
+                    let pool = pool.clone();
+                    thread::spawn(move || unsafe { pool.0.dealloc(index) })
+                })
+                .collect::<std::vec::Vec<_>>();
+
+            thread::yield_now();
+
+            let c = unsafe { alloc(&pool.0, 2).unwrap() };
+
+            for thread in threads {
+                thread.join().unwrap();
+            }
+
+            // all the memory is eventually reclaimed
+            assert_eq!(unsafe { drain(&pool.0, 1 << c) }, 0b111);
+        });
+    }
+
+    #[test]
+    fn aba() {
+        loom::model(|| {
+            let pool = Arc::new(Shared::<U2>(Pool::new()));
+
+            let a = unsafe { alloc(&pool.0, 0).unwrap() };
+            let b = unsafe { alloc(&pool.0, 1).unwrap() };
+
+            // a remote core pushes two slots ...
+            let remote = {
+                let pool = pool.clone();
+                thread::spawn(move || unsafe {
+                    pool.0.dealloc(a);
+                    pool.0.dealloc(b);
+                })
+            };
+
+            thread::yield_now();
+
+            // ... while the owner core keeps taking the head of the list and pushing it back
+            for _ in 0..2 {
+                if let Some(index) = unsafe { alloc(&pool.0, 2) } {
+                    unsafe { pool.0.dealloc(index) }
+                }
+            }
+
+            remote.join().unwrap();
+
+            assert_eq!(unsafe { drain(&pool.0, 0) }, 0b11);
+        });
+    }
+
+    #[test]
+    fn exhaustion() {
+        loom::model(|| {
+            let pool = Arc::new(Shared::<U1>(Pool::new()));
+
+            let a = unsafe { alloc(&pool.0, 0).unwrap() };
+
+            let remote = {
+                let pool = pool.clone();
+                thread::spawn(move || unsafe { pool.0.dealloc(a) })
+            };
+
+            thread::yield_now();
+
+            // the owner core either sees the pool exhausted or gets the freed slot
+            let b = unsafe { alloc(&pool.0, 1) };
+            assert!(b.is_none() || b == Some(a));
+
+            remote.join().unwrap();
+
+            let taken = if b.is_some() { 1 } else { 0 };
+            assert_eq!(unsafe { drain(&pool.0, taken) }, 1);
+        });
+    }
+
+    loom::lazy_static! {
+        static ref POOL: Pool<u32, U2> = Pool::new();
+    }
+
+    // `#[Singleton]` needs a `const` initializer; this handle points to a pool that loom re-creates
+    // on every execution of the model instead
+    struct P;
+
+    unsafe impl Singleton for P {
+        type Type = Pool<u32, U2>;
+
+        unsafe fn new() -> Self {
+            P
+        }
+
+        fn get() -> *mut Pool<u32, U2> {
+            &*POOL as *const _ as *mut _
+        }
+    }
+
+    impl ops::Deref for P {
+        type Target = Pool<u32, U2>;
+
+        fn deref(&self) -> &Pool<u32, U2> {
+            &POOL
+        }
+    }
+
+    unsafe impl StableDeref for P {}
+
+    #[test]
+    fn boxes() {
+        loom::model(|| {
+            let pool = unsafe { P::new() };
+
+            let a = Box::new(&pool, 0).ok().unwrap();
+            let b = Box::new(&pool, 1).ok().unwrap();
+
+            // a remote core drops its `Box` ...
+            let remote = thread::spawn(move || drop(a));
+
+            thread::yield_now();
+
+            // ... while the owner core frees and allocates through the public API
+            Box::free(b, &pool);
+            let c = Box::new(&pool, 2).ok().unwrap();
+            let d = Box::new(&pool, 3).ok();
+
+            remote.join().unwrap();
+
+            assert_eq!(*c, 2);
+            assert!(d.as_ref().map(|d| **d == 3).unwrap_or(true));
+
+            // the memory of `a` is reclaimed by the next allocation, if `d` didn't get it
+            let e = Box::new(&pool, 4).ok();
+            assert!(d.is_some() != e.is_some());
+        });
+    }
+}
@@ -0,0 +1,842 @@
+//! Ordered map whose entries are stored in a memory pool
+
+use core::{
+    borrow::Borrow,
+    cmp::{self, Ordering},
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{self, Bound, RangeBounds, RangeFull},
+    ptr,
+};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Pool, NIL};
+use crate::{error::AllocError, observer::PoolObserver};
+
+// An AVL tree of 255 nodes (the pool maximum) is at most 11 levels deep
+const MAX_HEIGHT: usize = 12;
+
+/// A slot of an `OrderedMap`
+///
+/// This is the element type of the memory pool that backs an `OrderedMap`
+pub struct Node<K, V> {
+    left: u8,
+    right: u8,
+    height: u8,
+    key: K,
+    value: V,
+}
+
+/// A map, sorted by key, that stores its entries on the memory pool `P`
+///
+/// The map is an AVL tree whose nodes are pool slots linked through their indices.
+///
+/// - Each entry takes exactly one slot: `insert` allocates at most one slot and never more, so a
+///   pool of `N` slots holds up to `N` entries
+/// - Lookups, insertions and removals are O(log n); the iterators use a fixed-size stack
+/// - The map owns the pool
+///
+/// *NOTE*: Entries that are still in the map when it's dropped are leaked; use `into_pool` to drop
+/// them
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{ordered_map::{Node, OrderedMap}, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Node<u32, &'static str>, U4> = Pool::new();
+///
+/// let mut timers = OrderedMap::new(unsafe { P::new() });
+///
+/// timers.insert(300, "c").unwrap();
+/// timers.insert(100, "a").unwrap();
+/// timers.insert(200, "b").unwrap();
+///
+/// // timers that expire before `250`
+/// assert!(timers.range(..250).map(|(_, t)| *t).eq(vec!["a", "b"]));
+///
+/// assert_eq!(timers.pop_first(), Some((100, "a")));
+/// assert_eq!(timers.first_key_value(), Some((&200, &"b")));
+/// ```
+pub struct OrderedMap<P>
+where
+    P: Singleton,
+{
+    pool: P,
+    root: u8,
+}
+
+impl<K, V, N, O, P> OrderedMap<P>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::DerefMut<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    O: PoolObserver,
+    K: Ord,
+{
+    /// Creates an empty map that stores its entries on the given `pool`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the `pool` has already been used to allocate `Box`es
+    pub fn new(pool: P) -> Self {
+        assert_eq!(pool.list.initialized, 0);
+
+        OrderedMap { pool, root: NIL }
+    }
+
+    /// Inserts the `key`-`value` pair into the map
+    ///
+    /// If the map already contained `key` its value is replaced and the old value is returned
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `key` and `value` is returned
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, AllocError<(K, V)>> {
+        let mut path = Path::new();
+        let mut index = self.root;
+        let mut less = false;
+        while index != NIL {
+            let node = self.get_node(index);
+
+            path.push(index);
+            index = match key.cmp(&node.key) {
+                Ordering::Less => {
+                    less = true;
+                    node.left
+                }
+                Ordering::Greater => {
+                    less = false;
+                    node.right
+                }
+                Ordering::Equal => {
+                    let old = mem::replace(&mut self.node_mut(index).value, value);
+                    return Ok(Some(old));
+                }
+            };
+        }
+
+        let index = match unsafe { self.pool.pop() } {
+            Some(index) => index,
+            None => return Err(AllocError::exhausted((key, value), N::USIZE, N::USIZE)),
+        };
+
+        unsafe {
+            ptr::write(
+                self.pool.slot(index),
+                Node {
+                    left: NIL,
+                    right: NIL,
+                    height: 1,
+                    key,
+                    value,
+                },
+            );
+        }
+
+        match path.last() {
+            None => self.root = index,
+            Some(parent) if less => self.node_mut(parent).left = index,
+            Some(parent) => self.node_mut(parent).right = index,
+        }
+
+        self.retrace(&path);
+
+        Ok(None)
+    }
+
+    /// Returns a mutable reference to the value associated to `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.search(key, &mut Path::new());
+
+        if index == NIL {
+            None
+        } else {
+            unsafe { Some(&mut (*self.pool.slot(index)).value) }
+        }
+    }
+
+    /// Removes `key` from the map and returns its value
+    ///
+    /// Returns `None` if the map didn't contain `key`
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes `key` from the map and returns the stored key and its value
+    ///
+    /// Returns `None` if the map didn't contain `key`
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut path = Path::new();
+        let index = self.search(key, &mut path);
+
+        if index == NIL {
+            None
+        } else {
+            Some(self.unlink(path, index))
+        }
+    }
+
+    /// Removes the entry with the smallest key from the map and returns it
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let mut path = Path::new();
+        let index = self.edge(true, &mut path);
+
+        if index == NIL {
+            None
+        } else {
+            Some(self.unlink(path, index))
+        }
+    }
+
+    /// Removes the entry with the largest key from the map and returns it
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let mut path = Path::new();
+        let index = self.edge(false, &mut path);
+
+        if index == NIL {
+            None
+        } else {
+            Some(self.unlink(path, index))
+        }
+    }
+
+    /// Removes all the entries from the map
+    pub fn clear(&mut self) {
+        while self.pop_last().is_some() {}
+    }
+
+    /// Drops all the entries of the map and returns the pool
+    ///
+    /// The returned pool can still allocate `Box`es but it can't back a new map
+    pub fn into_pool(mut self) -> P {
+        self.clear();
+        self.pool
+    }
+
+    // Removes the node `index`, whose ancestors are in `path`, from the tree and returns its slot
+    // to the pool
+    fn unlink(&mut self, mut path: Path, index: u8) -> (K, V) {
+        let (left, right) = {
+            let node = self.get_node(index);
+            (node.left, node.right)
+        };
+
+        // a node with two children trades its entry with its successor, which has no left child,
+        // and the successor is removed instead
+        let index = if left != NIL && right != NIL {
+            path.push(index);
+
+            let mut successor = right;
+            while self.get_node(successor).left != NIL {
+                path.push(successor);
+                successor = self.get_node(successor).left;
+            }
+
+            unsafe {
+                let (a, b) = (self.pool.slot(index), self.pool.slot(successor));
+                ptr::swap(&mut (*a).key, &mut (*b).key);
+                ptr::swap(&mut (*a).value, &mut (*b).value);
+            }
+
+            successor
+        } else {
+            index
+        };
+
+        let child = {
+            let node = self.get_node(index);
+            if node.left != NIL {
+                node.left
+            } else {
+                node.right
+            }
+        };
+        self.replace_child(path.last(), index, child);
+
+        let Node { key, value, .. } = unsafe { ptr::read(self.pool.slot(index)) };
+        unsafe { self.pool.push(index) }
+
+        self.retrace(&path);
+
+        (key, value)
+    }
+
+    // Rebalances the nodes of `path`, from the bottom up, after an insertion or removal below them
+    fn retrace(&mut self, path: &Path) {
+        for i in (0..path.len).rev() {
+            let index = path.nodes[i];
+            let new = self.rebalance(index);
+
+            if new != index {
+                let parent = if i == 0 {
+                    None
+                } else {
+                    Some(path.nodes[i - 1])
+                };
+                self.replace_child(parent, index, new);
+            }
+        }
+    }
+
+    fn replace_child(&mut self, parent: Option<u8>, old: u8, new: u8) {
+        match parent {
+            None => self.root = new,
+            Some(parent) => {
+                let parent = self.node_mut(parent);
+                if parent.left == old {
+                    parent.left = new;
+                } else {
+                    parent.right = new;
+                }
+            }
+        }
+    }
+
+    // Returns the root of the rebalanced subtree
+    fn rebalance(&mut self, index: u8) -> u8 {
+        self.update(index);
+
+        let (left, right) = {
+            let node = self.get_node(index);
+            (node.left, node.right)
+        };
+
+        if self.height(left) > self.height(right) + 1 {
+            let (ll, lr) = {
+                let node = self.get_node(left);
+                (node.left, node.right)
+            };
+
+            if self.height(ll) < self.height(lr) {
+                self.node_mut(index).left = self.rotate_left(left);
+            }
+
+            self.rotate_right(index)
+        } else if self.height(right) > self.height(left) + 1 {
+            let (rl, rr) = {
+                let node = self.get_node(right);
+                (node.left, node.right)
+            };
+
+            if self.height(rr) < self.height(rl) {
+                self.node_mut(index).right = self.rotate_right(right);
+            }
+
+            self.rotate_left(index)
+        } else {
+            index
+        }
+    }
+
+    fn rotate_left(&mut self, index: u8) -> u8 {
+        let right = self.get_node(index).right;
+
+        self.node_mut(index).right = self.get_node(right).left;
+        self.node_mut(right).left = index;
+        self.update(index);
+        self.update(right);
+
+        right
+    }
+
+    fn rotate_right(&mut self, index: u8) -> u8 {
+        let left = self.get_node(index).left;
+
+        self.node_mut(index).left = self.get_node(left).right;
+        self.node_mut(left).right = index;
+        self.update(index);
+        self.update(left);
+
+        left
+    }
+
+    fn update(&mut self, index: u8) {
+        let (left, right) = {
+            let node = self.get_node(index);
+            (node.left, node.right)
+        };
+
+        self.node_mut(index).height = 1 + cmp::max(self.height(left), self.height(right));
+    }
+
+    fn node_mut(&mut self, index: u8) -> &mut Node<K, V> {
+        unsafe { &mut *self.pool.slot(index) }
+    }
+}
+
+impl<K, V, N, O, P> OrderedMap<P>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::Deref<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    K: Ord,
+{
+    /// Returns `true` if the map contains `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value associated to `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.search(key, &mut Path::new());
+
+        if index == NIL {
+            None
+        } else {
+            unsafe { Some(&(*self.slot(index)).value) }
+        }
+    }
+
+    /// Returns the entry with the smallest key
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let index = self.edge(true, &mut Path::new());
+
+        if index == NIL {
+            None
+        } else {
+            let node = self.get_node(index);
+            Some((&node.key, &node.value))
+        }
+    }
+
+    /// Returns the entry with the largest key
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let index = self.edge(false, &mut Path::new());
+
+        if index == NIL {
+            None
+        } else {
+            let node = self.get_node(index);
+            Some((&node.key, &node.value))
+        }
+    }
+
+    /// Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        N::USIZE - usize::from(self.pool.list.free)
+    }
+
+    /// Returns `true` if the map contains no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the entries of the map, in key order
+    pub fn iter(&self) -> Range<'_, P> {
+        self.range::<K, RangeFull>(..)
+    }
+
+    /// Returns an iterator over the entries of the map whose keys are within `range`, in key order
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, P>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        // the ancestors of the first entry in range that precede it
+        let mut stack = Path::new();
+        let mut index = self.root;
+        while index != NIL {
+            let node = self.get_node(index);
+            let key = node.key.borrow();
+
+            let after_start = match range.start_bound() {
+                Bound::Included(start) => key >= start,
+                Bound::Excluded(start) => key > start,
+                Bound::Unbounded => true,
+            };
+
+            index = if after_start {
+                stack.push(index);
+                node.left
+            } else {
+                node.right
+            };
+        }
+
+        // the last entry in range
+        let mut last = NIL;
+        let mut index = self.root;
+        while index != NIL {
+            let node = self.get_node(index);
+            let key = node.key.borrow();
+
+            let before_end = match range.end_bound() {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+
+            index = if before_end {
+                last = index;
+                node.right
+            } else {
+                node.left
+            };
+        }
+
+        let empty = match stack.last() {
+            Some(first) => last == NIL || self.get_node(first).key > self.get_node(last).key,
+            None => true,
+        };
+
+        if empty {
+            stack.len = 0;
+            last = NIL;
+        }
+
+        Range {
+            _map: PhantomData,
+            last,
+            stack,
+        }
+    }
+
+    // Returns the node of `key`, or `NIL` if not found, and pushes its ancestors onto `path`
+    fn search<Q>(&self, key: &Q, path: &mut Path) -> u8
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut index = self.root;
+        while index != NIL {
+            let node = self.get_node(index);
+
+            let next = match key.cmp(node.key.borrow()) {
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+                Ordering::Equal => break,
+            };
+
+            path.push(index);
+            index = next;
+        }
+
+        index
+    }
+
+    // Returns the leftmost (`first`) or rightmost node, or `NIL` if the map is empty, and pushes its
+    // ancestors onto `path`
+    fn edge(&self, first: bool, path: &mut Path) -> u8 {
+        let mut index = self.root;
+        while index != NIL {
+            let node = self.get_node(index);
+            let next = if first { node.left } else { node.right };
+
+            if next == NIL {
+                break;
+            }
+
+            path.push(index);
+            index = next;
+        }
+
+        index
+    }
+
+    fn height(&self, index: u8) -> u8 {
+        if index == NIL {
+            0
+        } else {
+            self.get_node(index).height
+        }
+    }
+
+    fn get_node(&self, index: u8) -> &Node<K, V> {
+        unsafe { &*self.slot(index) }
+    }
+
+    fn slot(&self, index: u8) -> *const Node<K, V> {
+        unsafe { (self.pool.memory.as_ptr() as *const Node<K, V>).add(usize::from(index)) }
+    }
+}
+
+impl<K, V, N, O, P> fmt::Debug for OrderedMap<P>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>> + ops::Deref<Target = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    K: Ord + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over a range of the entries of an `OrderedMap`, in key order
+pub struct Range<'a, P>
+where
+    P: Singleton,
+{
+    _map: PhantomData<&'a P>,
+    // NOTE `NIL` once the iterator is done
+    last: u8,
+    stack: Path,
+}
+
+impl<'a, K, V, N, O, P> Iterator for Range<'a, P>
+where
+    P: Singleton<Type = Pool<Node<K, V>, N, O>>,
+    N: ArrayLength<Node<K, V>>,
+    K: 'a,
+    V: 'a,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        if self.last == NIL {
+            return None;
+        }
+
+        unsafe {
+            let index = self.stack.pop();
+            let node = &*Pool::slot_of::<P>(index);
+
+            // the successor is the leftmost node of the right subtree, if any
+            let mut child = node.right;
+            while child != NIL {
+                self.stack.push(child);
+                child = (*Pool::slot_of::<P>(child)).left;
+            }
+
+            if index == self.last {
+                self.last = NIL;
+            }
+
+            Some((&node.key, &node.value))
+        }
+    }
+}
+
+// A stack of node indices as deep as the tree can be
+struct Path {
+    len: usize,
+    nodes: [u8; MAX_HEIGHT],
+}
+
+impl Path {
+    fn new() -> Self {
+        Path {
+            len: 0,
+            nodes: [NIL; MAX_HEIGHT],
+        }
+    }
+
+    fn push(&mut self, index: u8) {
+        self.nodes[self.len] = index;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> u8 {
+        self.len -= 1;
+        self.nodes[self.len]
+    }
+
+    fn last(&self) -> Option<u8> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(self.nodes[self.len - 1])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        cmp,
+        ops::Bound,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use std::{collections::BTreeMap, vec::Vec};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Node, OrderedMap, NIL};
+    use crate::{error::Cause, nightly::pool::Pool};
+
+    // Checks the ordering and the balance of the subtree `index`; returns its height
+    fn check<V>(nodes: *const Node<u8, V>, index: u8) -> u8 {
+        if index == NIL {
+            return 0;
+        }
+
+        unsafe {
+            let node = &*nodes.add(usize::from(index));
+
+            for (child, ordering) in [
+                (node.left, cmp::Ordering::Less),
+                (node.right, cmp::Ordering::Greater),
+            ] {
+                if child != NIL {
+                    assert_eq!(
+                        (*nodes.add(usize::from(child))).key.cmp(&node.key),
+                        ordering
+                    );
+                }
+            }
+
+            let (left, right) = (check(nodes, node.left), check(nodes, node.right));
+            assert!(cmp::max(left, right) - cmp::min(left, right) <= 1);
+            assert_eq!(node.height, 1 + cmp::max(left, right));
+
+            node.height
+        }
+    }
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Node<i32, i32>, U4> = Pool::new();
+
+        let mut map = OrderedMap::new(unsafe { P::new() });
+        assert!(map.is_empty());
+        assert_eq!(map.pop_first(), None);
+
+        for &i in &[2, 0, 3, 1] {
+            assert_eq!(map.insert(i, i * 10).unwrap(), None);
+        }
+        assert_eq!(map.insert(0, -1).unwrap(), Some(0));
+
+        let e = map.insert(4, 40).unwrap_err();
+        assert_eq!(e.cause(), Cause::Exhausted);
+        assert_eq!(e.into_inner(), (4, 40));
+
+        *map.get_mut(&1).unwrap() += 1;
+        assert_eq!(map.get(&1), Some(&11));
+        assert!(!map.contains_key(&4));
+        assert_eq!(format!("{:?}", map), "{0: -1, 1: 11, 2: 20, 3: 30}");
+
+        assert_eq!(map.remove(&2), Some(20));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.last_key_value(), Some((&3, &30)));
+        assert_eq!(map.pop_last(), Some((3, 30)));
+        assert_eq!(map.pop_first(), Some((0, -1)));
+        assert_eq!(map.len(), 1);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.first_key_value(), None);
+    }
+
+    #[test]
+    fn range() {
+        #[Singleton]
+        static mut P: Pool<Node<u8, ()>, U16> = Pool::new();
+
+        let mut map = OrderedMap::new(unsafe { P::new() });
+        for i in 0..16 {
+            map.insert(i * 2, ()).unwrap();
+        }
+
+        let keys = |r: (Bound<u8>, Bound<u8>)| map.range(r).map(|(k, _)| *k).collect::<Vec<_>>();
+
+        assert_eq!(keys((Bound::Included(3), Bound::Excluded(9))), [4, 6, 8]);
+        assert_eq!(keys((Bound::Excluded(4), Bound::Included(10))), [6, 8, 10]);
+        assert_eq!(keys((Bound::Unbounded, Bound::Excluded(4))), [0, 2]);
+        assert_eq!(keys((Bound::Included(27), Bound::Unbounded)), [28, 30]);
+        assert!(keys((Bound::Included(5), Bound::Excluded(6))).is_empty());
+        assert!(keys((Bound::Included(31), Bound::Unbounded)).is_empty());
+        assert!(keys((Bound::Excluded(9), Bound::Excluded(3))).is_empty());
+        assert!(map.iter().map(|(k, _)| *k).eq((0..16).map(|i| i * 2)));
+    }
+
+    #[test]
+    fn balance() {
+        #[Singleton]
+        static mut P: Pool<Node<u8, u32>, U255> = Pool::new();
+
+        let mut map = OrderedMap::new(unsafe { P::new() });
+        let mut model = BTreeMap::new();
+
+        // pseudo-random mix of insertions and removals checked against `BTreeMap`
+        let mut seed = 1u32;
+        for i in 0..4096 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let key = (seed >> 16) as u8 % 255;
+
+            if seed % 3 == 0 {
+                assert_eq!(map.remove(&key), model.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, i).ok().unwrap(), model.insert(key, i));
+            }
+
+            let nodes = map.pool.memory.as_ptr() as *const Node<u8, u32>;
+            check(nodes, map.root);
+        }
+
+        // fill the pool: the tree must stay shallow enough for the fixed-size stacks
+        for key in 0..255 {
+            map.insert(key, 0).ok().unwrap();
+        }
+        assert_eq!(map.len(), 255);
+        assert!(map.iter().map(|(k, _)| *k).eq(0..255));
+    }
+
+    #[test]
+    fn destructor() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A;
+
+        impl A {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Node<u8, A>, U4> = Pool::new();
+
+        let mut map = OrderedMap::new(unsafe { P::new() });
+
+        for i in 0..4 {
+            map.insert(i, A::new()).ok().unwrap();
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+
+        // removing a node with two children must not drop its successor's value
+        drop(map.remove(&1));
+        assert_eq!(COUNT.load(Ordering::SeqCst), 3);
+
+        // giving the pool back drops the remaining values
+        let pool = map.into_pool();
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.list.free, 4);
+    }
+}
@@ -0,0 +1,519 @@
+//! Priority queue whose elements are stored in a memory pool
+
+use core::{
+    mem::{self, MaybeUninit},
+    ops, ptr,
+};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Pool, NIL};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A slot of a `PairingHeap`
+///
+/// This is the element type of the memory pool that backs a `PairingHeap`
+#[repr(C)]
+pub struct Node<T> {
+    // NOTE the pool stores the index of the next free slot in the first byte of the slot
+    child: u8,
+    // odd: the slot holds an element; even: vacant. Valid whether or not the slot is in use
+    generation: u32,
+    // the previous sibling or, for the leftmost child, the parent
+    prev: u8,
+    sibling: u8,
+    value: MaybeUninit<T>,
+}
+
+/// A handle to an element of a `PairingHeap`
+///
+/// A handle is invalidated when its element is popped or removed; it won't refer to an element
+/// that's later pushed into the same slot.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Handle {
+    index: u8,
+    generation: u32,
+}
+
+impl Handle {
+    /// Returns the index of the slot that holds the element
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
+/// A min-heap that stores its elements on the memory pool `P`
+///
+/// The heap is a pairing heap whose nodes are pool slots linked through their indices.
+///
+/// - `push`, `peek` and `decrease_key` are O(1); `pop` and `remove` are O(log n) amortized
+/// - `push` returns a `Handle` that can later be used to update, or remove, the element
+/// - The heap owns the pool
+///
+/// *NOTE*: Elements that are still in the heap when it's dropped are leaked; use `into_pool` to
+/// drop them
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{pairing_heap::{Node, PairingHeap}, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Node<(u32, &'static str)>, U4> = Pool::new();
+///
+/// // tasks ordered by deadline
+/// let mut tasks = PairingHeap::new(unsafe { P::new() });
+///
+/// tasks.push((30, "log")).unwrap();
+/// let sensor = tasks.push((20, "sensor")).unwrap();
+/// tasks.push((10, "radio")).unwrap();
+///
+/// // the sensor task became urgent
+/// tasks.decrease_key(sensor, (5, "sensor")).unwrap();
+///
+/// assert_eq!(tasks.pop(), Some((5, "sensor")));
+/// assert_eq!(tasks.pop(), Some((10, "radio")));
+/// assert_eq!(tasks.peek(), Some(&(30, "log")));
+/// ```
+pub struct PairingHeap<P>
+where
+    P: Singleton,
+{
+    pool: P,
+    root: u8,
+}
+
+impl<T, N, O, P> PairingHeap<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>> + ops::DerefMut<Target = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+    O: PoolObserver,
+    T: Ord,
+{
+    /// Creates an empty heap that stores its elements on the given `pool`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the `pool` has already been used to allocate `Box`es
+    pub fn new(mut pool: P) -> Self {
+        assert_eq!(pool.list.initialized, 0);
+
+        for index in 0..N::U8 {
+            unsafe { (*pool.slot(index)).generation = 0 }
+        }
+
+        PairingHeap { pool, root: NIL }
+    }
+
+    /// Pushes `value` onto the heap and returns a handle to it
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn push(&mut self, value: T) -> Result<Handle, AllocError<T>> {
+        unsafe {
+            let index = match self.pool.pop() {
+                Some(index) => index,
+                None => return Err(AllocError::exhausted(value, N::USIZE, N::USIZE)),
+            };
+
+            let node = self.pool.slot(index);
+            (*node).child = NIL;
+            (*node).generation = (*node).generation.wrapping_add(1);
+            (*node).prev = NIL;
+            (*node).sibling = NIL;
+            ptr::write((*node).value.as_mut_ptr(), value);
+
+            let generation = (*node).generation;
+            let root = self.root;
+            self.root = self.meld(root, index);
+
+            Ok(Handle { index, generation })
+        }
+    }
+
+    /// Removes the smallest element from the heap and returns it, or `None` if it's empty
+    pub fn pop(&mut self) -> Option<T> {
+        if self.root == NIL {
+            return None;
+        }
+
+        unsafe {
+            let root = self.root;
+            let child = (*self.pool.slot(root)).child;
+            self.root = self.merge_pairs(child);
+
+            Some(self.free(root))
+        }
+    }
+
+    /// Replaces the element `handle` refers to with the smaller, or equal, `value` and returns the
+    /// old element
+    ///
+    /// # Errors
+    ///
+    /// If `handle` no longer refers to an element of the heap an error containing `value` is
+    /// returned
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `value` is greater than the current element
+    pub fn decrease_key(&mut self, handle: Handle, value: T) -> Result<T, T> {
+        if !self.contains(handle) {
+            return Err(value);
+        }
+
+        unsafe {
+            let index = handle.index;
+            let current = &mut *(*self.pool.slot(index)).value.as_mut_ptr();
+            assert!(value <= *current);
+
+            let old = mem::replace(current, value);
+
+            if index != self.root {
+                self.cut(index);
+
+                let root = self.root;
+                self.root = self.meld(root, index);
+            }
+
+            Ok(old)
+        }
+    }
+
+    /// Removes the element `handle` refers to from the heap and returns it
+    ///
+    /// Returns `None` if `handle` no longer refers to an element of the heap
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        if !self.contains(handle) {
+            return None;
+        }
+
+        let index = handle.index;
+        if index == self.root {
+            return self.pop();
+        }
+
+        unsafe {
+            self.cut(index);
+
+            let child = (*self.pool.slot(index)).child;
+            let subheap = self.merge_pairs(child);
+            let root = self.root;
+            self.root = self.meld(root, subheap);
+
+            Some(self.free(index))
+        }
+    }
+
+    /// Removes all the elements from the heap
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Drops all the elements of the heap and returns the pool
+    ///
+    /// The returned pool can still allocate `Box`es but it can't back a new heap
+    pub fn into_pool(mut self) -> P {
+        self.clear();
+        self.pool
+    }
+
+    // Links two heap-ordered trees, whose roots have no siblings, and returns the root of the result
+    unsafe fn meld(&mut self, a: u8, b: u8) -> u8 {
+        if a == NIL {
+            return b;
+        } else if b == NIL {
+            return a;
+        }
+
+        let (parent, child) =
+            if *(*self.pool.slot(b)).value.as_ptr() < *(*self.pool.slot(a)).value.as_ptr() {
+                (b, a)
+            } else {
+                (a, b)
+            };
+
+        // `child` becomes the leftmost child of `parent`
+        let first = (*self.pool.slot(parent)).child;
+        if first != NIL {
+            (*self.pool.slot(first)).prev = child;
+        }
+
+        let node = self.pool.slot(child);
+        (*node).prev = parent;
+        (*node).sibling = first;
+
+        let node = self.pool.slot(parent);
+        (*node).child = child;
+        (*node).prev = NIL;
+        (*node).sibling = NIL;
+
+        parent
+    }
+
+    // Melds the list of siblings that starts at `first` into a single tree, two at a time from the
+    // left and then one at a time from the right
+    unsafe fn merge_pairs(&mut self, first: u8) -> u8 {
+        // the melded pairs, in reverse order, linked through `sibling`
+        let mut pairs = NIL;
+
+        let mut current = first;
+        while current != NIL {
+            let a = current;
+            let b = (*self.pool.slot(a)).sibling;
+
+            let pair = if b == NIL {
+                current = NIL;
+                a
+            } else {
+                current = (*self.pool.slot(b)).sibling;
+                self.meld(a, b)
+            };
+
+            (*self.pool.slot(pair)).sibling = pairs;
+            pairs = pair;
+        }
+
+        let mut root = NIL;
+        while pairs != NIL {
+            let pair = pairs;
+            pairs = (*self.pool.slot(pair)).sibling;
+            (*self.pool.slot(pair)).sibling = NIL;
+
+            root = self.meld(root, pair);
+        }
+
+        if root != NIL {
+            let node = self.pool.slot(root);
+            (*node).prev = NIL;
+            (*node).sibling = NIL;
+        }
+
+        root
+    }
+
+    // Detaches the subtree rooted at `index`, which must not be the root, from its parent
+    unsafe fn cut(&mut self, index: u8) {
+        let (prev, sibling) = {
+            let node = self.pool.slot(index);
+            ((*node).prev, (*node).sibling)
+        };
+
+        if (*self.pool.slot(prev)).child == index {
+            (*self.pool.slot(prev)).child = sibling;
+        } else {
+            (*self.pool.slot(prev)).sibling = sibling;
+        }
+
+        if sibling != NIL {
+            (*self.pool.slot(sibling)).prev = prev;
+        }
+
+        let node = self.pool.slot(index);
+        (*node).prev = NIL;
+        (*node).sibling = NIL;
+    }
+
+    // Moves the element out of the slot `index` and returns the slot to the pool
+    unsafe fn free(&mut self, index: u8) -> T {
+        let node = self.pool.slot(index);
+        (*node).generation = (*node).generation.wrapping_add(1);
+        let value = ptr::read((*node).value.as_ptr());
+
+        self.pool.push(index);
+
+        value
+    }
+}
+
+impl<T, N, O, P> PairingHeap<P>
+where
+    P: Singleton<Type = Pool<Node<T>, N, O>> + ops::Deref<Target = Pool<Node<T>, N, O>>,
+    N: ArrayLength<Node<T>>,
+{
+    /// Returns `true` if `handle` refers to an element of the heap
+    pub fn contains(&self, handle: Handle) -> bool {
+        handle.index < N::U8
+            && unsafe { (*self.slot(handle.index)).generation } == handle.generation
+    }
+
+    /// Returns a reference to the element `handle` refers to
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        if self.contains(handle) {
+            unsafe { Some(&*(*self.slot(handle.index)).value.as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the smallest element of the heap, or `None` if it's empty
+    pub fn peek(&self) -> Option<&T> {
+        if self.root == NIL {
+            None
+        } else {
+            unsafe { Some(&*(*self.slot(self.root)).value.as_ptr()) }
+        }
+    }
+
+    /// Returns the number of elements in the heap
+    pub fn len(&self) -> usize {
+        N::USIZE - usize::from(self.pool.list.free)
+    }
+
+    /// Returns `true` if the heap contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.root == NIL
+    }
+
+    fn slot(&self, index: u8) -> *const Node<T> {
+        unsafe { (self.pool.memory.as_ptr() as *const Node<T>).add(usize::from(index)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::vec::Vec;
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Node, PairingHeap};
+    use crate::{error::Cause, nightly::pool::Pool};
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Node<i32>, U4> = Pool::new();
+
+        let mut heap = PairingHeap::new(unsafe { P::new() });
+        assert_eq!(heap.pop(), None);
+        assert_eq!(heap.peek(), None);
+
+        let a = heap.push(3).unwrap();
+        let b = heap.push(1).unwrap();
+        let c = heap.push(4).unwrap();
+        let d = heap.push(2).unwrap();
+
+        let e = heap.push(0).unwrap_err();
+        assert_eq!(e.cause(), Cause::Exhausted);
+        assert_eq!(e.into_inner(), 0);
+
+        assert_eq!(heap.peek(), Some(&1));
+        assert_eq!(heap.decrease_key(c, 0), Ok(4));
+        assert_eq!(heap.get(c), Some(&0));
+        assert_eq!(heap.remove(a), Some(3));
+        assert_eq!(heap.remove(a), None);
+        assert_eq!(heap.len(), 3);
+
+        assert_eq!(heap.pop(), Some(0));
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.decrease_key(b, -1), Err(-1));
+        assert_eq!(heap.pop(), Some(2));
+        assert!(heap.is_empty());
+
+        // `f` reuses the slot of `d` but `d`'s handle doesn't give access to it
+        let f = heap.push(5).unwrap();
+        assert_eq!(f.index(), d.index());
+        assert_eq!(heap.get(d), None);
+        assert_eq!(heap.decrease_key(d, 6), Err(6));
+        assert_eq!(heap.remove(d), None);
+        assert_eq!(heap.get(f), Some(&5));
+        assert_eq!(heap.pop(), Some(5));
+    }
+
+    #[test]
+    fn model() {
+        #[Singleton]
+        static mut P: Pool<Node<(u32, u32)>, U64> = Pool::new();
+
+        let mut heap = PairingHeap::new(unsafe { P::new() });
+        // the live elements and their handles; elements are made unique by a sequence number
+        let mut model = Vec::new();
+
+        // pseudo-random mix of operations checked against a sorted `Vec`
+        let mut seed = 1u32;
+        for id in 0..8192 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let value = (seed >> 16) % 1000;
+
+            match seed % 4 {
+                0 | 1 => match heap.push((value, id)) {
+                    Ok(handle) => model.push(((value, id), handle)),
+                    Err(_) => assert_eq!(model.len(), 64),
+                },
+                2 if !model.is_empty() => {
+                    let i = value as usize % model.len();
+                    let (old, handle) = model[i];
+
+                    if value % 2 == 0 {
+                        assert_eq!(heap.remove(handle), Some(old));
+                        model.swap_remove(i);
+                    } else {
+                        let new = (old.0 / 2, old.1);
+                        assert_eq!(heap.decrease_key(handle, new), Ok(old));
+                        model[i].0 = new;
+                    }
+                }
+                _ => {
+                    model.sort();
+                    let min = if model.is_empty() {
+                        None
+                    } else {
+                        Some(model.remove(0).0)
+                    };
+
+                    assert_eq!(heap.pop(), min);
+                }
+            }
+
+            assert_eq!(heap.len(), model.len());
+            assert_eq!(heap.peek(), model.iter().map(|(v, _)| v).min());
+        }
+    }
+
+    #[test]
+    fn destructor() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Eq, Ord, PartialEq, PartialOrd)]
+        pub struct A(u8);
+
+        impl A {
+            fn new(x: u8) -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A(x)
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Node<A>, U4> = Pool::new();
+
+        let mut heap = PairingHeap::new(unsafe { P::new() });
+
+        for i in 0..4 {
+            heap.push(A::new(i)).ok().unwrap();
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+
+        drop(heap.pop());
+        assert_eq!(COUNT.load(Ordering::SeqCst), 3);
+
+        // giving the pool back drops the remaining elements
+        let pool = heap.into_pool();
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.list.free, 4);
+    }
+}
@@ -0,0 +1,316 @@
+//! Object cache that resets objects instead of dropping and rebuilding them
+
+use core::{fmt, marker::PhantomData, ops, ptr};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Pool, NIL};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// Objects that can be handed out again, after a `reset`, once their user is done with them
+pub trait Recycle {
+    /// Brings the object back to the state a new user expects; called when the object is returned
+    /// to a `Cache`
+    fn reset(&mut self);
+}
+
+/// A slot of a `Cache`
+///
+/// This is the element type of the memory pool that backs a `Cache`
+pub struct Slot<T> {
+    // next recycled object; only meaningful while the object sits in the cache
+    next: u8,
+    value: T,
+}
+
+/// An object allocated from the `Cache` that owns the memory pool `P`
+///
+/// - `Object` must be explicitly returned to the cache (`Cache::recycle` or `Cache::free`) or its
+///   slot will be leaked
+/// - `sizeof(Object<_>)` is a single byte
+pub struct Object<P>
+where
+    P: Singleton,
+{
+    _not_send_or_sync: PhantomData<*const ()>,
+    _pool: PhantomData<P>,
+    index: u8,
+}
+
+impl<T, N, O, P> ops::Deref for Object<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &(*Pool::slot_of::<P>(self.index)).value }
+    }
+}
+
+impl<T, N, O, P> ops::DerefMut for Object<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut (*Pool::slot_of::<P>(self.index)).value }
+    }
+}
+
+unsafe impl<T, N, O, P> Send for Object<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+    T: Send,
+{
+}
+
+impl<T, N, O, P> fmt::Debug for Object<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt(&**self, f)
+    }
+}
+
+/// A cache of objects constructed on the memory pool `P`
+///
+/// Objects returned to the cache with `recycle` are `reset` and stay constructed in their slots;
+/// the next allocation hands out one of them instead of building a new object. This is meant for
+/// objects that are expensive to construct.
+///
+/// *NOTE*: Objects that are still in the cache when it's dropped are leaked; use `into_pool` to
+/// drop them
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{recycle::{Cache, Recycle, Slot}, Pool},
+/// };
+///
+/// struct Parser {
+///     table: [u16; 256],
+///     state: u8,
+/// }
+///
+/// impl Recycle for Parser {
+///     fn reset(&mut self) {
+///         // keep the table
+///         self.state = 0;
+///     }
+/// }
+///
+/// #[Singleton]
+/// static mut P: Pool<Slot<Parser>, U2> = Pool::new();
+///
+/// let mut cache = Cache::new(unsafe { P::new() });
+///
+/// let build = || Parser { table: [0xbeef; 256], state: 0 };
+///
+/// let mut parser = cache.alloc_with(build).ok().unwrap();
+/// parser.state = 3;
+/// cache.recycle(parser);
+///
+/// // the same parser, minus its state, is handed out again; `build` isn't called
+/// let parser = cache.alloc_with(|| unreachable!()).ok().unwrap();
+/// assert_eq!(parser.state, 0);
+/// assert_eq!(parser.table[0], 0xbeef);
+///
+/// // drop the object and return its slot to the pool
+/// cache.free(parser);
+/// ```
+pub struct Cache<P>
+where
+    P: Singleton,
+{
+    head: u8,
+    pool: P,
+    recycled: u8,
+}
+
+impl<T, N, O, P> Cache<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>> + ops::DerefMut<Target = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+    O: PoolObserver,
+    T: Recycle,
+{
+    /// Creates an empty cache that constructs its objects on the given `pool`
+    pub fn new(pool: P) -> Self {
+        Cache {
+            head: NIL,
+            pool,
+            recycled: 0,
+        }
+    }
+
+    /// Hands out a recycled object or, if there's none, constructs a new object with `init`
+    ///
+    /// `init` is only called when a new object needs to be constructed
+    ///
+    /// # Errors
+    ///
+    /// If there's no recycled object and the memory pool has been exhausted an error containing
+    /// `init` is returned
+    pub fn alloc_with<F>(&mut self, init: F) -> Result<Object<P>, AllocError<F>>
+    where
+        F: FnOnce() -> T,
+    {
+        let index = if self.head != NIL {
+            let index = self.head;
+            self.head = unsafe { (*self.pool.slot(index)).next };
+            self.recycled -= 1;
+
+            index
+        } else if self.pool.list.free == 0 {
+            let live = N::USIZE - usize::from(self.recycled);
+            return Err(AllocError::exhausted(init, live, N::USIZE));
+        } else {
+            // NOTE construct the object first so a panicking `init` doesn't leak the slot
+            let value = init();
+
+            unsafe {
+                let index = self.pool.pop().unwrap();
+                ptr::write(self.pool.slot(index), Slot { next: NIL, value });
+
+                index
+            }
+        };
+
+        Ok(Object {
+            _not_send_or_sync: PhantomData,
+            _pool: PhantomData,
+            index,
+        })
+    }
+
+    /// Resets the `object` and keeps it, constructed, in the cache for a later allocation
+    pub fn recycle(&mut self, object: Object<P>) {
+        unsafe {
+            let slot = &mut *self.pool.slot(object.index);
+            slot.value.reset();
+            slot.next = self.head;
+        }
+
+        self.head = object.index;
+        self.recycled += 1;
+    }
+
+    /// Drops the `object` and returns its slot to the pool
+    pub fn free(&mut self, object: Object<P>) {
+        unsafe {
+            ptr::drop_in_place(self.pool.slot(object.index));
+            self.pool.push(object.index);
+        }
+    }
+
+    /// Drops all the recycled objects and returns their slots to the pool
+    pub fn purge(&mut self) {
+        while self.head != NIL {
+            let index = self.head;
+
+            unsafe {
+                self.head = (*self.pool.slot(index)).next;
+                ptr::drop_in_place(self.pool.slot(index));
+                self.pool.push(index);
+            }
+        }
+
+        self.recycled = 0;
+    }
+
+    /// Drops all the recycled objects and returns the pool
+    ///
+    /// The objects handed out by this cache stay valid; they can be freed, or recycled, through a
+    /// new cache built on the returned pool
+    pub fn into_pool(mut self) -> P {
+        self.purge();
+        self.pool
+    }
+
+    /// Returns the number of recycled objects waiting in the cache
+    pub fn recycled(&self) -> u8 {
+        self.recycled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Cache, Recycle, Slot};
+    use crate::{error::Cause, nightly::pool::Pool};
+
+    #[test]
+    fn recycle() {
+        static BUILT: AtomicUsize = AtomicUsize::new(0);
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        static RESET: AtomicUsize = AtomicUsize::new(0);
+
+        struct A(u32);
+
+        impl A {
+            fn new() -> Self {
+                BUILT.fetch_add(1, Ordering::SeqCst);
+                A(0)
+            }
+        }
+
+        impl Recycle for A {
+            fn reset(&mut self) {
+                RESET.fetch_add(1, Ordering::SeqCst);
+                self.0 = 0;
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Slot<A>, U2> = Pool::new();
+
+        let mut cache = Cache::new(unsafe { P::new() });
+
+        let mut a = cache.alloc_with(A::new).ok().unwrap();
+        let b = cache.alloc_with(A::new).ok().unwrap();
+        let e = cache.alloc_with(A::new).err().unwrap();
+        assert_eq!(e.cause(), Cause::Exhausted);
+        assert_eq!(BUILT.load(Ordering::SeqCst), 2);
+
+        a.0 = 42;
+        cache.recycle(a);
+        assert_eq!(cache.recycled(), 1);
+        assert_eq!(RESET.load(Ordering::SeqCst), 1);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+
+        // the recycled object is handed out instead of a new one
+        let a = cache.alloc_with(A::new).ok().unwrap();
+        assert_eq!(a.0, 0);
+        assert_eq!(BUILT.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.recycled(), 0);
+
+        cache.free(a);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+
+        // giving the pool back drops the recycled objects
+        cache.recycle(b);
+        let pool = cache.into_pool();
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.list.free, 2);
+    }
+}
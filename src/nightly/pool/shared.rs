@@ -0,0 +1,608 @@
+//! Fixed size memory pool that can be shared between threads (requires the `std` feature)
+//!
+//! This pool has the same API as `unsend::Pool` but it's backed by a `std::sync::Mutex` so it can
+//! be used from several threads at once. It's meant for running code written against the embedded
+//! pools in multi-threaded host tests and simulators.
+
+pub mod quota;
+
+use core::{
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops,
+    pin::Pin,
+    ptr,
+};
+use std::sync::{Mutex, MutexGuard};
+
+use generic_array::{
+    typenum::{consts::U256, IsLess, True},
+    ArrayLength, GenericArray,
+};
+use owned_singleton::Singleton;
+
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+    stats::PoolStats,
+    sync::UnsafeCell,
+};
+
+/// A value allocated on the memory pool `P`
+///
+/// - `Box` destructor returns the memory to the pool `P`
+/// - `sizeof(Box<_>)` is a single byte
+/// - `Box<P>` implements `Send` if `P` is a pool of values that implement `Send`
+/// - `Box<P>` implements `Sync` if `P` is a pool of values that implement `Sync`
+pub struct Box<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    _not_send_or_sync: PhantomData<*const ()>,
+    _pool: PhantomData<P>,
+    index: u8,
+}
+
+impl<P> Drop for Box<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    fn drop(&mut self) {
+        use self::sealed::Dealloc;
+
+        unsafe { (*P::get()).dealloc(self.index) }
+    }
+}
+
+impl<T, N, O, P> ops::Deref for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(*P::get()).slot(self.index) }
+    }
+}
+
+impl<T, N, O, P> ops::DerefMut for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(*P::get()).slot(self.index) }
+    }
+}
+
+impl<T, N, O, P> Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Pins the value in place
+    ///
+    /// This is free: pool slots never move and the value is dropped in place when the `Box` is
+    /// freed
+    pub fn into_pin(b: Self) -> Pin<Self> {
+        unsafe { Pin::new_unchecked(b) }
+    }
+}
+
+impl<T, N, O, P> Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Allocates the given `value` on the pool
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn new(pool: &P, value: T) -> Result<Box<P>, AllocError<T>> {
+        assert!(mem::size_of::<T>() > 0);
+
+        if let Some(index) = pool.take(false) {
+            unsafe { ptr::write(pool.slot(index), value) }
+
+            Ok(Box {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                index,
+            })
+        } else {
+            O::on_exhausted();
+
+            Err(AllocError::exhausted(value, N::USIZE, N::USIZE))
+        }
+    }
+
+    /// Allocates the given `value` on the pool and pins it in place
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn pin(pool: &P, value: T) -> Result<Pin<Box<P>>, AllocError<T>> {
+        Box::new(pool, value).map(Box::into_pin)
+    }
+
+    /// Returns a `serde` seed that deserializes a value straight into the `pool`
+    ///
+    /// Deserialization fails if the pool has been exhausted
+    #[cfg(feature = "serde")]
+    pub fn seed(pool: &P) -> BoxSeed<'_, P> {
+        BoxSeed { pool }
+    }
+
+    /// Moves the value into the pool `dst` and returns its slot to its current pool
+    ///
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing this `Box` is returned
+    pub fn transfer<M, R, Q>(self, dst: &Q) -> Result<Box<Q>, Box<P>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::Deref<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+    {
+        if let Some(index) = dst.take(false) {
+            unsafe {
+                let src = &*P::get();
+                ptr::copy_nonoverlapping(src.slot(self.index), dst.slot(index), 1);
+                src.release(self.index);
+            }
+            mem::forget(self);
+
+            Ok(Box {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                index,
+            })
+        } else {
+            R::on_exhausted();
+
+            Err(self)
+        }
+    }
+
+    /// Allocates a clone of the value on the pool `dst`
+    ///
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing the clone is returned
+    pub fn try_clone_in<M, R, Q>(&self, dst: &Q) -> Result<Box<Q>, AllocError<T>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::Deref<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+        T: Clone,
+    {
+        Box::new(dst, T::clone(self))
+    }
+}
+
+// NOTE moving the `Box` doesn't move the value, which stays in its slot
+impl<P> Unpin for Box<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+}
+
+unsafe impl<T, N, O, P> Send for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: Send,
+{
+}
+
+unsafe impl<T, N, O, P> Sync for Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: Sync,
+{
+}
+
+forward_box_traits!(
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver
+);
+
+/// Slots of the memory pool `P` set aside for later, infallible, allocations (see `Pool::reserve`)
+///
+/// - `Reservation` can be sent to, and used from, any thread
+/// - `Reservation` destructor returns the slots that were not used to the pool `P`
+pub struct Reservation<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    _pool: PhantomData<P>,
+    len: u8,
+}
+
+impl<T, N, O, P> Reservation<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Allocates the given `value` on one of the reserved slots
+    ///
+    /// # Panics
+    ///
+    /// This method panics if all the reserved slots have already been used (see `try_alloc`)
+    pub fn alloc(&mut self, value: T) -> Box<P> {
+        assert!(self.len != 0, "all the reserved slots have been used");
+
+        self.len -= 1;
+
+        unsafe {
+            let pool = &*P::get();
+
+            let index = pool.take(true).unwrap();
+            ptr::write(pool.slot(index), value);
+
+            Box {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                index,
+            }
+        }
+    }
+
+    /// Allocates the given `value` on one of the reserved slots
+    ///
+    /// # Errors
+    ///
+    /// If all the reserved slots have already been used an error containing `value` is returned
+    pub fn try_alloc(&mut self, value: T) -> Result<Box<P>, AllocError<T>> {
+        if self.len == 0 {
+            Err(AllocError::new(value, Cause::ReservationMissing))
+        } else {
+            Ok(self.alloc(value))
+        }
+    }
+
+    /// Returns the number of reserved slots that have not been used yet
+    pub fn len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns `true` if all the reserved slots have been used
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<P> Drop for Reservation<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    fn drop(&mut self) {
+        use self::sealed::Dealloc;
+
+        unsafe { (*P::get()).unreserve(self.len) }
+    }
+}
+
+/// A fixed-size memory pool that can be shared between threads
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+///
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{consts::*, pool::shared::{Box, Pool}};
+///
+/// #[Singleton(Send)]
+/// static P: Pool<[u8; 128], U4> = Pool::new();
+///
+/// let pool = unsafe { P::new() };
+///
+/// let buffer: Box<P> = Box::new(&pool, [0; 128]).ok().unwrap();
+///
+/// thread::spawn(move || {
+///     let other: Box<P> = Box::new(&pool, [1; 128]).ok().unwrap();
+///
+///     // the memory returns to the pool when the `Box`es are dropped
+///     drop((buffer, other));
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub struct Pool<T, N, O = ()>
+where
+    N: ArrayLength<T>,
+{
+    _observer: PhantomData<O>,
+    memory: UnsafeCell<MaybeUninit<GenericArray<T, N>>>,
+    state: Mutex<State>,
+}
+
+struct State {
+    // free slots that are not reserved
+    free: u8,
+    head: u8,
+    initialized: u8,
+    // free slots set aside by `Reservation`s
+    reserved: u8,
+}
+
+impl<T, N, O> Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Reserves `k` slots of the `pool` for the exclusive use of the returned `Reservation`
+    ///
+    /// The reserved slots are removed from the pool's free count so other `Box::new` calls can't
+    /// take them.
+    ///
+    /// # Errors
+    ///
+    /// If the pool has less than `k` free slots nothing is reserved and an error containing the
+    /// number of free slots is returned
+    pub fn reserve<P>(pool: &P, k: u8) -> Result<Reservation<P>, u8>
+    where
+        P: Singleton<Type = Self> + ops::Deref<Target = Self>,
+    {
+        assert!(mem::size_of::<T>() > 0);
+
+        let mut state = pool.state();
+        if k > state.free {
+            return Err(state.free);
+        }
+
+        state.free -= k;
+        state.reserved += k;
+
+        Ok(Reservation {
+            _pool: PhantomData,
+            len: k,
+        })
+    }
+
+    /// Returns a snapshot of the usage of the pool
+    pub fn stats(&self) -> PoolStats {
+        let state = self.state();
+
+        PoolStats {
+            capacity: N::U8,
+            live: N::U8 - state.free - state.reserved,
+            reserved: state.reserved,
+        }
+    }
+
+    // NOTE the state is updated atomically with respect to panics so a poisoned lock is still
+    // consistent
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn slot(&self, index: u8) -> *mut T {
+        self.memory
+            .with_mut(|memory| unsafe { (memory as *mut T).add(usize::from(index)) })
+    }
+
+    // Takes a slot off the free list; a `reserved` slot must have been set aside by a reservation
+    fn take(&self, reserved: bool) -> Option<u8> {
+        let index = {
+            let mut state = self.state();
+
+            if reserved {
+                state.reserved -= 1;
+            } else if state.free == 0 {
+                return None;
+            } else {
+                state.free -= 1;
+            }
+
+            unsafe {
+                if state.initialized < N::U8 {
+                    let index = state.initialized;
+
+                    *(self.slot(index) as *mut u8) = index + 1;
+                    state.initialized += 1;
+                }
+
+                let index = state.head;
+                state.head = *(self.slot(index) as *const u8);
+
+                index
+            }
+        };
+
+        O::on_alloc(index);
+
+        Some(index)
+    }
+
+    // Puts a slot back on the free list; the value it held must have been dropped or moved out
+    unsafe fn release(&self, index: u8) {
+        {
+            let mut state = self.state();
+
+            *(self.slot(index) as *mut u8) = state.head;
+
+            state.free += 1;
+            state.head = index;
+        }
+
+        O::on_free(index);
+    }
+}
+
+unsafe impl<T, N, O> sealed::Dealloc for Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    unsafe fn dealloc(&self, index: u8) {
+        ptr::drop_in_place(self.slot(index));
+
+        self.release(index);
+    }
+
+    fn unreserve(&self, k: u8) {
+        let mut state = self.state();
+
+        state.free += k;
+        state.reserved -= k;
+    }
+}
+
+impl<T, N, O> Pool<T, N, O>
+where
+    N: ArrayLength<T> + IsLess<U256, Output = True>,
+{
+    loom_const_fn! {
+        /// Creates a new memory pool
+        pub fn new() -> Self {
+            Pool {
+                _observer: PhantomData,
+                memory: UnsafeCell::new(MaybeUninit::uninitialized()),
+                state: Mutex::new(State {
+                    free: N::U8,
+                    head: 0,
+                    initialized: 0,
+                    reserved: 0,
+                }),
+            }
+        }
+    }
+}
+
+// NOTE values can be dropped, through their `Box`es, from any thread
+unsafe impl<T, N, O> Sync for Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    T: Send,
+{
+}
+
+mod sealed {
+    pub unsafe trait Dealloc {
+        unsafe fn dealloc(&self, value: u8);
+
+        fn unreserve(&self, k: u8);
+    }
+}
+
+/// Deserializes a value into a `Box` allocated on the memory pool `P` (see `Box::seed`)
+#[cfg(feature = "serde")]
+pub struct BoxSeed<'a, P>
+where
+    P: Singleton,
+{
+    pool: &'a P,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a, T, N, O, P> serde::de::DeserializeSeed<'de> for BoxSeed<'a, P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: serde::Deserialize<'de>,
+{
+    type Value = Box<P>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Box<P>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+
+        Box::new(self.pool, value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::{sync::Barrier, thread, vec::Vec};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Box, Pool};
+    use crate::error::Cause;
+
+    #[test]
+    fn threads() {
+        #[Singleton(Send, Sync)]
+        static P: Pool<u32, U8> = Pool::new();
+
+        let pool = unsafe { P::new() };
+        let barrier = Barrier::new(4);
+
+        // four threads allocate two values each at the same time and then free them on another
+        // thread
+        let boxes = thread::scope(|s| {
+            let threads = (0..4)
+                .map(|i| {
+                    let (barrier, pool) = (&barrier, &pool);
+
+                    s.spawn(move || {
+                        barrier.wait();
+
+                        let a = Box::new(pool, 2 * i).unwrap();
+                        let b = Box::new(pool, 2 * i + 1).unwrap();
+                        (a, b)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            threads
+                .into_iter()
+                .map(|t| t.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let mut values = boxes
+            .iter()
+            .flat_map(|(a, b)| vec![**a, **b])
+            .collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(Box::new(&pool, 8).is_err());
+
+        thread::spawn(move || drop(boxes)).join().unwrap();
+        assert_eq!(pool.stats().live, 0);
+    }
+
+    #[test]
+    fn reserve() {
+        #[Singleton(Send)]
+        static P: Pool<i32, U2> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        let mut reservation = Pool::reserve(pool, 1).unwrap();
+        let _0 = Box::new(pool, 0).unwrap();
+        assert!(Box::new(pool, 1).is_err());
+
+        let r = thread::spawn(move || {
+            let x = *reservation.alloc(2);
+
+            (x, reservation.try_alloc(3).err().map(|e| e.cause()))
+        })
+        .join();
+        assert_eq!(r.unwrap(), (2, Some(Cause::ReservationMissing)));
+        assert_eq!(pool.stats().live, 1);
+    }
+}
@@ -0,0 +1,247 @@
+//! Per-consumer limits on the number of live values allocated on a pool shared between threads
+
+use core::{fmt, ops};
+use std::sync::{Mutex, MutexGuard};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Box, Pool};
+pub use crate::nightly::pool::unsend::quota::Stats;
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+};
+
+/// A consumer of the memory pool `P` that can hold at most `max` live values at any time
+///
+/// This is the thread-safe counterpart of `unsend::quota::Quota`: a `Quota` can be shared between
+/// threads and its `QuotaBox`es can be dropped from any of them.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+///
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{consts::*, pool::shared::{quota::Quota, Pool}};
+///
+/// #[Singleton(Send, Sync)]
+/// static P: Pool<[u8; 64], U8> = Pool::new();
+///
+/// let pool = unsafe { P::new() };
+///
+/// let uart = Quota::new(&pool, 2);
+///
+/// let a = uart.alloc([0; 64]).ok().unwrap();
+/// let b = uart.alloc([1; 64]).ok().unwrap();
+///
+/// // UART has reached its quota ...
+/// assert!(uart.alloc([2; 64]).is_err());
+///
+/// // ... until one of its values is released, which can be done from another thread
+/// thread::scope(|s| {
+///     s.spawn(move || drop(a));
+/// });
+/// let c = uart.alloc([2; 64]).ok().unwrap();
+///
+/// let stats = uart.stats();
+/// assert_eq!((stats.live, stats.over_quota), (2, 1));
+/// ```
+pub struct Quota<'p, P>
+where
+    P: Singleton,
+{
+    pool: &'p P,
+    stats: Mutex<Stats>,
+}
+
+impl<'p, T, N, O, P> Quota<'p, P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Creates a quota that can hold at most `max` live values allocated on the `pool`
+    pub fn new(pool: &'p P, max: u8) -> Self {
+        Quota {
+            pool,
+            stats: Mutex::new(Stats {
+                exhausted: 0,
+                live: 0,
+                max,
+                over_quota: 0,
+                peak: 0,
+            }),
+        }
+    }
+
+    /// Allocates the given `value` on the pool
+    ///
+    /// # Errors
+    ///
+    /// If the quota has been reached, or the memory pool has been exhausted, an error containing
+    /// `value` is returned
+    pub fn alloc(&self, value: T) -> Result<QuotaBox<'_, P>, AllocError<T>> {
+        // NOTE the lock is held across `Box::new` so concurrent callers can't overshoot `max`
+        let mut stats = lock(&self.stats);
+        if stats.live >= stats.max {
+            stats.over_quota = stats.over_quota.wrapping_add(1);
+            return Err(AllocError::new(value, Cause::QuotaExceeded)
+                .with_usage(usize::from(stats.live), usize::from(stats.max)));
+        }
+
+        match Box::new(self.pool, value) {
+            Ok(inner) => {
+                stats.live += 1;
+                if stats.live > stats.peak {
+                    stats.peak = stats.live;
+                }
+
+                Ok(QuotaBox {
+                    inner,
+                    stats: &self.stats,
+                })
+            }
+            Err(e) => {
+                stats.exhausted = stats.exhausted.wrapping_add(1);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'p, P> Quota<'p, P>
+where
+    P: Singleton,
+{
+    /// Returns the usage statistics of this quota
+    pub fn stats(&self) -> Stats {
+        *lock(&self.stats)
+    }
+}
+
+/// A value allocated through a `Quota`
+///
+/// - `QuotaBox` destructor returns the memory to the pool and gives the slot back to the quota
+pub struct QuotaBox<'q, P>
+where
+    P: Singleton,
+    P::Type: super::sealed::Dealloc,
+{
+    inner: Box<P>,
+    stats: &'q Mutex<Stats>,
+}
+
+impl<'q, P> Drop for QuotaBox<'q, P>
+where
+    P: Singleton,
+    P::Type: super::sealed::Dealloc,
+{
+    fn drop(&mut self) {
+        lock(self.stats).live -= 1;
+    }
+}
+
+impl<'q, T, N, O, P> ops::Deref for QuotaBox<'q, P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<'q, T, N, O, P> ops::DerefMut for QuotaBox<'q, P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<'q, T, N, O, P> fmt::Debug for QuotaBox<'q, P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+// NOTE the counters are updated atomically with respect to panics so a poisoned lock is still
+// consistent
+fn lock(stats: &Mutex<Stats>) -> MutexGuard<'_, Stats> {
+    stats.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::{sync::Barrier, thread, vec::Vec};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Quota, Stats};
+    use crate::{error::Cause, nightly::pool::shared::Pool};
+
+    #[test]
+    fn threads() {
+        #[Singleton(Send, Sync)]
+        static P: Pool<u32, U8> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        let a = Quota::new(pool, 3);
+        let b = Quota::new(pool, 4);
+        let barrier = Barrier::new(2);
+
+        // both consumers try to allocate past their quota at the same time
+        thread::scope(|s| {
+            for &quota in &[&a, &b] {
+                let barrier = &barrier;
+
+                s.spawn(move || {
+                    let boxes = (0..5)
+                        .filter_map(|i| quota.alloc(i).ok())
+                        .collect::<Vec<_>>();
+
+                    barrier.wait();
+                    drop(boxes);
+                });
+            }
+        });
+
+        assert_eq!(
+            a.stats(),
+            Stats {
+                exhausted: 0,
+                live: 0,
+                max: 3,
+                over_quota: 2,
+                peak: 3,
+            }
+        );
+        assert_eq!((b.stats().peak, b.stats().over_quota), (4, 1));
+        assert_eq!(pool.stats().live, 0);
+
+        let c = Quota::new(pool, 2);
+        let _a = (0..3).map(|i| a.alloc(i).unwrap()).collect::<Vec<_>>();
+        let _b = (0..4).map(|i| b.alloc(i).unwrap()).collect::<Vec<_>>();
+        let _c = c.alloc(7).unwrap();
+
+        // the quota allows it but the pool is exhausted
+        assert_eq!(c.alloc(8).err().map(|e| e.cause()), Some(Cause::Exhausted));
+        assert_eq!(c.stats().exhausted, 1);
+    }
+}
@@ -0,0 +1,351 @@
+//! Map that hands out copyable keys to values stored in a memory pool
+
+use core::{fmt, marker::PhantomData, mem::MaybeUninit, ops, ptr};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::Pool;
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A slot of a `SlotMap`
+///
+/// This is the element type of the memory pool that backs a `SlotMap`
+#[repr(C)]
+pub struct Slot<T> {
+    // NOTE the pool stores the index of the next free slot in the first byte of the slot
+    _link: u8,
+    // odd: occupied; even: vacant
+    generation: u32,
+    value: MaybeUninit<T>,
+}
+
+/// A handle to a value stored in a `SlotMap`
+///
+/// A `Key` is invalidated when its value is removed from the map; it won't give access to a value
+/// that's later inserted into the same slot.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Key {
+    index: u8,
+    generation: u32,
+}
+
+impl Key {
+    /// Returns the index of the slot this key refers to
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
+/// A map that stores its values on the memory pool `P` and identifies them with `Key`s
+///
+/// Unlike `Box`, which owns its value, `Key` is a `Copy` handle that can be freely shared;
+/// removing a value invalidates all the copies of its `Key`.
+///
+/// *NOTE*: Values that are still in the map when it's dropped are leaked; use `into_pool` to drop
+/// them
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{slot_map::{Slot, SlotMap}, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Slot<&'static str>, U4> = Pool::new();
+///
+/// let mut map = SlotMap::new(unsafe { P::new() });
+///
+/// let foo = map.insert("foo").unwrap();
+/// let bar = map.insert("bar").unwrap();
+///
+/// assert_eq!(map.get(foo), Some(&"foo"));
+/// assert_eq!(map.remove(foo), Some("foo"));
+///
+/// // `foo`'s slot is reused but the old key is no longer valid
+/// let baz = map.insert("baz").unwrap();
+/// assert_eq!(foo.index(), baz.index());
+/// assert_eq!(map.get(foo), None);
+/// assert_eq!(map.get(baz), Some(&"baz"));
+/// ```
+pub struct SlotMap<P>
+where
+    P: Singleton,
+{
+    pool: P,
+}
+
+impl<T, N, O, P> SlotMap<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>> + ops::DerefMut<Target = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+    O: PoolObserver,
+{
+    /// Creates an empty map that stores its values on the given `pool`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the `pool` has already been used to allocate `Box`es
+    pub fn new(pool: P) -> Self {
+        assert_eq!(pool.list.initialized, 0);
+
+        SlotMap { pool }
+    }
+
+    /// Inserts `value` into the map and returns its key
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn insert(&mut self, value: T) -> Result<Key, AllocError<T>> {
+        unsafe {
+            // the pool is about to lazily initialize this slot; start its generation count
+            if self.pool.list.initialized < N::U8 {
+                let index = self.pool.list.initialized;
+                (*self.pool.slot(index)).generation = 0;
+            }
+
+            if let Some(index) = self.pool.pop() {
+                let slot = &mut *self.pool.slot(index);
+                slot.generation = slot.generation.wrapping_add(1);
+                ptr::write(slot.value.as_mut_ptr(), value);
+
+                Ok(Key {
+                    index,
+                    generation: slot.generation,
+                })
+            } else {
+                Err(AllocError::exhausted(value, N::USIZE, N::USIZE))
+            }
+        }
+    }
+
+    /// Removes the value associated to `key` from the map and returns it
+    ///
+    /// Returns `None` if `key` has already been removed
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        unsafe {
+            let slot = &mut *self.pool.slot(key.index);
+            slot.generation = slot.generation.wrapping_add(1);
+            let value = ptr::read(slot.value.as_ptr());
+
+            self.pool.push(key.index);
+
+            Some(value)
+        }
+    }
+
+    /// Returns a mutable reference to the value associated to `key`
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        if self.contains_key(key) {
+            unsafe { Some(&mut *(*self.pool.slot(key.index)).value.as_mut_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Removes all the values from the map
+    pub fn clear(&mut self) {
+        for index in 0..self.pool.list.initialized {
+            let generation = unsafe { (*self.get_slot(index)).generation };
+
+            if generation % 2 == 1 {
+                self.remove(Key { index, generation });
+            }
+        }
+    }
+
+    /// Drops all the values of the map and returns the pool
+    ///
+    /// The returned pool can still allocate `Box`es but it can't back a new map
+    pub fn into_pool(mut self) -> P {
+        self.clear();
+        self.pool
+    }
+}
+
+impl<T, N, O, P> SlotMap<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>> + ops::Deref<Target = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+{
+    /// Returns `true` if the map contains a value associated to `key`
+    pub fn contains_key(&self, key: Key) -> bool {
+        key.index < self.pool.list.initialized
+            && unsafe { (*self.get_slot(key.index)).generation } == key.generation
+    }
+
+    /// Returns a reference to the value associated to `key`
+    pub fn get(&self, key: Key) -> Option<&T> {
+        if self.contains_key(key) {
+            unsafe { Some(&*(*self.get_slot(key.index)).value.as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of values in the map
+    pub fn len(&self) -> usize {
+        N::USIZE - usize::from(self.pool.list.free)
+    }
+
+    /// Returns `true` if the map contains no values
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the keys and values of the map, ordered by slot index
+    pub fn iter(&self) -> Iter<'_, P> {
+        Iter {
+            _map: PhantomData,
+            index: 0,
+        }
+    }
+
+    fn get_slot(&self, index: u8) -> *const Slot<T> {
+        unsafe { (self.pool.memory.as_ptr() as *const Slot<T>).add(usize::from(index)) }
+    }
+}
+
+impl<T, N, O, P> fmt::Debug for SlotMap<P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>> + ops::Deref<Target = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over the keys and values of a `SlotMap`
+pub struct Iter<'a, P>
+where
+    P: Singleton,
+{
+    _map: PhantomData<&'a SlotMap<P>>,
+    index: u8,
+}
+
+impl<'a, T, N, O, P> Iterator for Iter<'a, P>
+where
+    P: Singleton<Type = Pool<Slot<T>, N, O>>,
+    N: ArrayLength<Slot<T>>,
+    T: 'a,
+{
+    type Item = (Key, &'a T);
+
+    fn next(&mut self) -> Option<(Key, &'a T)> {
+        unsafe {
+            let pool = &*P::get();
+
+            while self.index < pool.list.initialized {
+                let index = self.index;
+                self.index += 1;
+
+                let slot = &*Pool::slot_of::<P>(index);
+                if slot.generation % 2 == 1 {
+                    let key = Key {
+                        index,
+                        generation: slot.generation,
+                    };
+
+                    return Some((key, &*slot.value.as_ptr()));
+                }
+            }
+
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Slot, SlotMap};
+    use crate::{error::Cause, nightly::pool::Pool};
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Slot<i32>, U3> = Pool::new();
+
+        let mut map = SlotMap::new(unsafe { P::new() });
+
+        let a = map.insert(0).unwrap();
+        let b = map.insert(1).unwrap();
+        let c = map.insert(2).unwrap();
+        let e = map.insert(3).err().unwrap();
+        assert_eq!(
+            (e.cause(), e.capacity(), e.into_inner()),
+            (Cause::Exhausted, Some(3), 3)
+        );
+        assert_eq!(map.len(), 3);
+
+        *map.get_mut(b).unwrap() += 10;
+        assert_eq!(map.remove(b), Some(11));
+        assert_eq!(map.remove(b), None);
+        assert_eq!(map.get(b), None);
+
+        let d = map.insert(4).unwrap();
+        assert_eq!(d.index(), b.index());
+        assert_ne!(d, b);
+        assert_eq!(map.get(b), None);
+        assert_eq!(map.get_mut(b), None);
+        assert_eq!(map.get(d), Some(&4));
+
+        assert_eq!(
+            map.iter().collect::<std::vec::Vec<_>>(),
+            [(a, &0), (d, &4), (c, &2)]
+        );
+    }
+
+    #[test]
+    fn destructor() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A;
+
+        impl A {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Slot<A>, U4> = Pool::new();
+
+        let mut map = SlotMap::new(unsafe { P::new() });
+
+        let a = map.insert(A::new()).ok().unwrap();
+        let _b = map.insert(A::new()).ok().unwrap();
+        let _c = map.insert(A::new()).ok().unwrap();
+        assert_eq!(COUNT.load(Ordering::SeqCst), 3);
+
+        drop(map.remove(a));
+        assert_eq!(COUNT.load(Ordering::SeqCst), 2);
+
+        // giving the pool back drops the remaining values
+        let pool = map.into_pool();
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.list.free, 4);
+    }
+}
@@ -0,0 +1,341 @@
+//! Growable UTF-8 string whose bytes live in chunks allocated on a memory pool
+
+use core::{fmt, ops, str};
+
+use generic_array::{
+    typenum::{consts::U256, IsLess, True},
+    ArrayLength,
+};
+use owned_singleton::Singleton;
+
+use super::{
+    vec::{self, Chunk, Vec},
+    Pool,
+};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A string whose bytes are stored in chunks allocated on the memory pool `P`
+///
+/// - `String` must be explicitly freed (`String::free`) or the chunks it holds will be leaked
+/// - As the bytes are not contiguous in memory `String` doesn't deref to `str`; use the `chars`
+///   and `bytes` iterators instead
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{string::String, vec::Chunk, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Chunk<u8, U8>, U2> = Pool::new();
+///
+/// let mut pool = unsafe { P::new() };
+///
+/// let mut s: String<P> = String::new();
+///
+/// s.push_str(&mut pool, "Hello, ").unwrap();
+/// s.push_str(&mut pool, "world").unwrap();
+/// s.push(&mut pool, '!').unwrap();
+///
+/// // not enough space left
+/// assert!(s.push_str(&mut pool, " Bye!").is_err());
+///
+/// assert_eq!(s, *"Hello, world!");
+///
+/// // return the chunks to the pool or the memory will be leaked
+/// s.free(&mut pool);
+/// ```
+pub struct String<P>
+where
+    P: Singleton,
+{
+    vec: Vec<P>,
+}
+
+impl<C, N, O, P> String<P>
+where
+    P: Singleton<Type = Pool<Chunk<u8, C>, N, O>>
+        + ops::DerefMut<Target = Pool<Chunk<u8, C>, N, O>>,
+    C: ArrayLength<u8> + IsLess<U256, Output = True>,
+    N: ArrayLength<Chunk<u8, C>>,
+    O: PoolObserver,
+{
+    /// Creates an empty string; this doesn't allocate
+    pub fn new() -> Self {
+        String { vec: Vec::new() }
+    }
+
+    /// Appends the character `c` to the back of the string
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `c` is returned
+    pub fn push(&mut self, pool: &mut P, c: char) -> Result<(), AllocError<char>> {
+        let mut buf = [0; 4];
+        let s: &str = c.encode_utf8(&mut buf);
+
+        self.push_str(pool, s).map_err(|e| e.map(|_| c))
+    }
+
+    /// Appends the string `s` to the back of the string
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool can't hold all of `s` an error containing `s` is returned and the string
+    /// is left unchanged
+    pub fn push_str<'s>(&mut self, pool: &mut P, s: &'s str) -> Result<(), AllocError<&'s str>> {
+        if self.vec.spare(pool) < s.len() {
+            let live = N::USIZE - usize::from(pool.list.free);
+
+            return Err(AllocError::exhausted(s, live, N::USIZE));
+        }
+
+        for byte in s.bytes() {
+            // NOTE(ok) enough space was checked above
+            self.vec.push(pool, byte).ok();
+        }
+
+        Ok(())
+    }
+
+    /// Removes the last character of the string and returns it, or `None` if it's empty
+    pub fn pop(&mut self, pool: &mut P) -> Option<char> {
+        let mut buf = [0; 4];
+        let mut start = buf.len();
+
+        loop {
+            let byte = self.vec.pop(pool)?;
+            start -= 1;
+            buf[start] = byte;
+
+            if !is_continuation(byte) {
+                break;
+            }
+        }
+
+        decode(&buf[start..])
+    }
+
+    /// Removes all the characters of the string and returns its chunks to the `pool`
+    pub fn clear(&mut self, pool: &mut P) {
+        self.vec.clear(pool)
+    }
+
+    /// Returns the chunks held by the string to the `pool`
+    pub fn free(self, pool: &mut P) {
+        self.vec.free(pool)
+    }
+}
+
+impl<P> String<P>
+where
+    P: Singleton,
+{
+    /// Returns the length of the string in bytes
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns `true` if the string is empty
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Returns an iterator over the bytes of the string
+    pub fn bytes(&self) -> Bytes<'_, P> {
+        Bytes {
+            iter: self.vec.iter(),
+        }
+    }
+
+    /// Returns an iterator over the characters of the string
+    pub fn chars(&self) -> Chars<'_, P> {
+        Chars {
+            iter: self.vec.iter(),
+        }
+    }
+}
+
+impl<C, N, O, P> Default for String<P>
+where
+    P: Singleton<Type = Pool<Chunk<u8, C>, N, O>>
+        + ops::DerefMut<Target = Pool<Chunk<u8, C>, N, O>>,
+    C: ArrayLength<u8> + IsLess<U256, Output = True>,
+    N: ArrayLength<Chunk<u8, C>>,
+    O: PoolObserver,
+{
+    fn default() -> Self {
+        String::new()
+    }
+}
+
+impl<C, N, O, P> PartialEq<str> for String<P>
+where
+    P: Singleton<Type = Pool<Chunk<u8, C>, N, O>>,
+    C: ArrayLength<u8>,
+    N: ArrayLength<Chunk<u8, C>>,
+{
+    fn eq(&self, other: &str) -> bool {
+        self.bytes().eq(other.bytes())
+    }
+}
+
+impl<C, N, O, P> fmt::Debug for String<P>
+where
+    P: Singleton<Type = Pool<Chunk<u8, C>, N, O>>,
+    C: ArrayLength<u8>,
+    N: ArrayLength<Chunk<u8, C>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use core::fmt::Write;
+
+        f.write_char('"')?;
+        for c in self.chars() {
+            for e in c.escape_debug() {
+                f.write_char(e)?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+impl<C, N, O, P> fmt::Display for String<P>
+where
+    P: Singleton<Type = Pool<Chunk<u8, C>, N, O>>,
+    C: ArrayLength<u8>,
+    N: ArrayLength<Chunk<u8, C>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use core::fmt::Write;
+
+        for c in self.chars() {
+            f.write_char(c)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Iterator over the bytes of a `String`
+pub struct Bytes<'a, P>
+where
+    P: Singleton,
+{
+    iter: vec::Iter<'a, P>,
+}
+
+impl<'a, C, N, O, P> Iterator for Bytes<'a, P>
+where
+    P: Singleton<Type = Pool<Chunk<u8, C>, N, O>>,
+    C: ArrayLength<u8>,
+    N: ArrayLength<Chunk<u8, C>>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.iter.next().cloned()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Iterator over the characters of a `String`
+pub struct Chars<'a, P>
+where
+    P: Singleton,
+{
+    iter: vec::Iter<'a, P>,
+}
+
+impl<'a, C, N, O, P> Iterator for Chars<'a, P>
+where
+    P: Singleton<Type = Pool<Chunk<u8, C>, N, O>>,
+    C: ArrayLength<u8>,
+    N: ArrayLength<Chunk<u8, C>>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let first = *self.iter.next()?;
+
+        let width = if first < 0x80 {
+            1
+        } else if first < 0xE0 {
+            2
+        } else if first < 0xF0 {
+            3
+        } else {
+            4
+        };
+
+        let mut buf = [first; 4];
+        for byte in &mut buf[1..width] {
+            *byte = *self.iter.next()?;
+        }
+
+        decode(&buf[..width])
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+// `bytes` is always a single, valid, UTF-8 encoded character
+fn decode(bytes: &[u8]) -> Option<char> {
+    unsafe { str::from_utf8_unchecked(bytes) }.chars().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::String;
+    use crate::{
+        error::Cause,
+        nightly::pool::{vec::Chunk, Pool},
+    };
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Chunk<u8, U3>, U4> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut s: String<P> = String::new();
+        assert_eq!(s.pop(pool), None);
+
+        // multi-byte characters straddle chunks
+        s.push_str(pool, "añ€").unwrap();
+        s.push(pool, '𝄞').unwrap();
+        assert_eq!(s.len(), 10);
+        assert_eq!(pool.list.free, 0);
+        assert!(s.chars().eq("añ€𝄞".chars()));
+
+        // all or nothing
+        let e = s.push_str(pool, "abc").err().unwrap();
+        assert_eq!(
+            (e.cause(), e.live(), e.into_inner()),
+            (Cause::Exhausted, Some(4), "abc")
+        );
+        assert_eq!(s.len(), 10);
+        assert_eq!(s.push(pool, '€').err().unwrap().into_inner(), '€');
+
+        assert_eq!(s.pop(pool), Some('𝄞'));
+        assert_eq!(pool.list.free, 2);
+        assert_eq!(s.pop(pool), Some('€'));
+        assert_eq!(s.pop(pool), Some('ñ'));
+        assert_eq!(s, *"a");
+        assert_eq!(format!("{:?}", s), "\"a\"");
+
+        s.free(pool);
+        assert_eq!(pool.list.free, 4);
+    }
+}
@@ -1,10 +1,14 @@
 //! Fixed size memory pool with automatic deallocation of handles
 
+pub mod quota;
+
 use core::{
-    cell::{Cell, UnsafeCell},
+    cell::Cell,
     marker::PhantomData,
     mem::{self, MaybeUninit},
-    ops, ptr,
+    ops,
+    pin::Pin,
+    ptr,
 };
 
 use generic_array::{
@@ -12,13 +16,21 @@ use generic_array::{
     ArrayLength, GenericArray,
 };
 use owned_singleton::Singleton;
-use stable_deref_trait::StableDeref;
+
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+    stats::PoolStats,
+    sync::UnsafeCell,
+};
 
 /// A value allocated on the memory pool `P`
 ///
 /// - `Box` never implements the `Send` or `Sync` traits.
 /// - `Box` destructor returns the memory to the pool `P`
 /// - `sizeof(Box<_>)` is a single byte
+/// - `Box<P>` can be handed to DMA APIs (`embedded_dma::{ReadBuffer, WriteBuffer}`) if it derefs to
+///   a word buffer like `[u16; N]` or `Aligned<A, [u8; N]>` (requires the `embedded-dma` feature)
 pub struct Box<P>
 where
     P: Singleton,
@@ -41,59 +53,65 @@ where
     }
 }
 
-impl<T, N, P> ops::Deref for Box<P>
+impl<T, N, O, P> ops::Deref for Box<P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
+    O: PoolObserver,
 {
     type Target = T;
 
     fn deref(&self) -> &T {
-        unsafe { &*((*P::get()).memory.get() as *const T).add(usize::from(self.index)) }
+        unsafe { &*(*P::get()).slot(self.index) }
     }
 }
 
-impl<T, N, P> ops::DerefMut for Box<P>
+impl<T, N, O, P> ops::DerefMut for Box<P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
+    O: PoolObserver,
 {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *((*P::get()).memory.get() as *mut T).add(usize::from(self.index)) }
+        unsafe { &mut *(*P::get()).slot(self.index) }
     }
 }
 
-impl<T, N, P> Box<P>
+impl<T, N, O, P> Box<P>
 where
-    P: Singleton<Type = Pool<T, N>> + ops::Deref<Target = Pool<T, N>>,
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Pins the value in place
+    ///
+    /// This is free: pool slots never move and the value is dropped in place when the `Box` is
+    /// freed
+    pub fn into_pin(b: Self) -> Pin<Self> {
+        unsafe { Pin::new_unchecked(b) }
+    }
+}
+
+impl<T, N, O, P> Box<P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
 {
     /// Allocates the given `value` on the pool
     ///
     /// # Errors
     ///
     /// If the memory pool has been exhausted an error containing `value` is returned
-    pub fn new(pool: &P, value: T) -> Result<Box<P>, T> {
+    pub fn new(pool: &P, value: T) -> Result<Box<P>, AllocError<T>> {
         unsafe {
             assert!(mem::size_of::<T>() > 0);
 
-            if pool.initialized.get() < N::U8 {
-                let index = pool.initialized.get();
-
-                let p = (pool.memory.get() as *mut T).add(usize::from(index));
-
-                *(p as *mut u8) = index + 1;
-                pool.initialized.set(index + 1);
-            }
-
             if pool.free.get() != 0 {
-                let index = pool.head.get();
-                let p = (pool.memory.get() as *mut T).add(usize::from(index));
-                pool.head.set(*(p as *const u8));
-
                 pool.free.set(pool.free.get() - 1);
 
-                ptr::write(p, value);
+                let index = pool.take();
+                ptr::write(pool.slot(index), value);
 
                 Ok(Box {
                     _not_send_or_sync: PhantomData,
@@ -101,18 +119,175 @@ where
                     index,
                 })
             } else {
-                Err(value)
+                O::on_exhausted();
+
+                Err(AllocError::exhausted(value, N::USIZE, N::USIZE))
             }
         }
     }
+
+    /// Allocates the given `value` on the pool and pins it in place
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool has been exhausted an error containing `value` is returned
+    pub fn pin(pool: &P, value: T) -> Result<Pin<Box<P>>, AllocError<T>> {
+        Box::new(pool, value).map(Box::into_pin)
+    }
+
+    /// Returns a `serde` seed that deserializes a value straight into the `pool`
+    ///
+    /// Deserialization fails if the pool has been exhausted
+    #[cfg(feature = "serde")]
+    pub fn seed(pool: &P) -> BoxSeed<'_, P> {
+        BoxSeed { pool }
+    }
+
+    /// Moves the value into the pool `dst` and returns its slot to its current pool
+    ///
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing this `Box` is returned
+    pub fn transfer<M, R, Q>(self, dst: &Q) -> Result<Box<Q>, Box<P>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::Deref<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+    {
+        if dst.free.get() == 0 {
+            R::on_exhausted();
+
+            return Err(self);
+        }
+
+        unsafe {
+            dst.free.set(dst.free.get() - 1);
+
+            let index = dst.take();
+            let src = &*P::get();
+            ptr::copy_nonoverlapping(src.slot(self.index), dst.slot(index), 1);
+            src.release(self.index);
+            mem::forget(self);
+
+            Ok(Box {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                index,
+            })
+        }
+    }
+
+    /// Allocates a clone of the value on the pool `dst`
+    ///
+    /// # Errors
+    ///
+    /// If `dst` has been exhausted an error containing the clone is returned
+    pub fn try_clone_in<M, R, Q>(&self, dst: &Q) -> Result<Box<Q>, AllocError<T>>
+    where
+        Q: Singleton<Type = Pool<T, M, R>> + ops::Deref<Target = Pool<T, M, R>>,
+        M: ArrayLength<T>,
+        R: PoolObserver,
+        T: Clone,
+    {
+        Box::new(dst, T::clone(self))
+    }
 }
 
-unsafe impl<T, N, P> StableDeref for Box<P>
+// NOTE moving the `Box` doesn't move the value, which stays in its slot
+impl<P> Unpin for Box<P>
 where
-    P: Singleton<Type = Pool<T, N>>,
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+}
+
+forward_box_traits!(
+    P: Singleton<Type = Pool<T, N, O>>,
     N: ArrayLength<T>,
+    O: PoolObserver
+);
+
+/// Slots of the memory pool `P` set aside for later, infallible, allocations (see `Pool::reserve`)
+///
+/// - `Reservation` never implements the `Send` or `Sync` traits.
+/// - `Reservation` destructor returns the slots that were not used to the pool `P`
+pub struct Reservation<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
 {
+    _not_send_or_sync: PhantomData<*const ()>,
+    _pool: PhantomData<P>,
+    len: u8,
+}
+
+impl<T, N, O, P> Reservation<P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Allocates the given `value` on one of the reserved slots
+    ///
+    /// # Panics
+    ///
+    /// This method panics if all the reserved slots have already been used (see `try_alloc`)
+    pub fn alloc(&mut self, value: T) -> Box<P> {
+        assert!(self.len != 0, "all the reserved slots have been used");
+
+        self.len -= 1;
+
+        unsafe {
+            let pool = &*P::get();
+
+            pool.reserved.set(pool.reserved.get() - 1);
+            let index = pool.take();
+            ptr::write(pool.slot(index), value);
+
+            Box {
+                _not_send_or_sync: PhantomData,
+                _pool: PhantomData,
+                index,
+            }
+        }
+    }
+
+    /// Allocates the given `value` on one of the reserved slots
+    ///
+    /// # Errors
+    ///
+    /// If all the reserved slots have already been used an error containing `value` is returned
+    pub fn try_alloc(&mut self, value: T) -> Result<Box<P>, AllocError<T>> {
+        if self.len == 0 {
+            Err(AllocError::new(value, Cause::ReservationMissing))
+        } else {
+            Ok(self.alloc(value))
+        }
+    }
+
+    /// Returns the number of reserved slots that have not been used yet
+    pub fn len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns `true` if all the reserved slots have been used
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
+
+impl<P> Drop for Reservation<P>
+where
+    P: Singleton,
+    P::Type: sealed::Dealloc,
+{
+    fn drop(&mut self) {
+        use self::sealed::Dealloc;
+
+        unsafe { (*P::get()).unreserve(self.len) }
+    }
+}
+
 /// A fixed-size memory pool that can NOT be sent across threads
 ///
 /// # Example
@@ -133,45 +308,128 @@ where
 /// // return the memory to the pool
 /// drop(buffer);
 /// ```
-pub struct Pool<T, N>
+pub struct Pool<T, N, O = ()>
 where
     N: ArrayLength<T>,
 {
     _not_send_or_sync: PhantomData<*const ()>,
+    _observer: PhantomData<O>,
     free: Cell<u8>,
     head: Cell<u8>,
     initialized: Cell<u8>,
     memory: UnsafeCell<MaybeUninit<GenericArray<T, N>>>,
+    // free slots set aside by `Reservation`s; they are not included in `free`
+    reserved: Cell<u8>,
 }
 
-unsafe impl<T, N> sealed::Dealloc for Pool<T, N>
+impl<T, N, O> Pool<T, N, O>
 where
     N: ArrayLength<T>,
+    O: PoolObserver,
 {
-    unsafe fn dealloc(&self, index: u8) {
-        let p = (self.memory.get() as *mut T).add(usize::from(index));
+    /// Reserves `k` slots of the `pool` for the exclusive use of the returned `Reservation`
+    ///
+    /// The reserved slots are removed from the pool's free count so other `Box::new` calls can't
+    /// take them.
+    ///
+    /// # Errors
+    ///
+    /// If the pool has less than `k` free slots nothing is reserved and an error containing the
+    /// number of free slots is returned
+    pub fn reserve<P>(pool: &P, k: u8) -> Result<Reservation<P>, u8>
+    where
+        P: Singleton<Type = Self> + ops::Deref<Target = Self>,
+    {
+        assert!(mem::size_of::<T>() > 0);
+
+        let free = pool.free.get();
+        if k > free {
+            return Err(free);
+        }
+
+        pool.free.set(free - k);
+        pool.reserved.set(pool.reserved.get() + k);
+
+        Ok(Reservation {
+            _not_send_or_sync: PhantomData,
+            _pool: PhantomData,
+            len: k,
+        })
+    }
+
+    /// Returns a snapshot of the usage of the pool
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            capacity: N::U8,
+            live: N::U8 - self.free.get() - self.reserved.get(),
+            reserved: self.reserved.get(),
+        }
+    }
+
+    fn slot(&self, index: u8) -> *mut T {
+        self.memory
+            .with_mut(|memory| unsafe { (memory as *mut T).add(usize::from(index)) })
+    }
+
+    // Takes a slot off the free list; the caller must have already accounted for it in `free`
+    unsafe fn take(&self) -> u8 {
+        if self.initialized.get() < N::U8 {
+            let index = self.initialized.get();
+
+            *(self.slot(index) as *mut u8) = index + 1;
+            self.initialized.set(index + 1);
+        }
+
+        let index = self.head.get();
+        self.head.set(*(self.slot(index) as *const u8));
+        O::on_alloc(index);
 
-        ptr::drop_in_place(p);
+        index
+    }
 
-        *(p as *mut u8) = self.head.get();
+    // Puts a slot back on the free list; the value it held must have been dropped or moved out
+    unsafe fn release(&self, index: u8) {
+        *(self.slot(index) as *mut u8) = self.head.get();
 
         self.free.set(self.free.get() + 1);
         self.head.set(index);
+        O::on_free(index);
+    }
+}
+
+unsafe impl<T, N, O> sealed::Dealloc for Pool<T, N, O>
+where
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    unsafe fn dealloc(&self, index: u8) {
+        ptr::drop_in_place(self.slot(index));
+
+        self.release(index);
+    }
+
+    fn unreserve(&self, k: u8) {
+        self.free.set(self.free.get() + k);
+        self.reserved.set(self.reserved.get() - k);
     }
 }
 
-impl<T, N> Pool<T, N>
+impl<T, N, O> Pool<T, N, O>
 where
     N: ArrayLength<T> + IsLess<U256, Output = True>,
 {
-    /// Creates a new memory pool
-    pub const fn new() -> Self {
-        Pool {
-            _not_send_or_sync: PhantomData,
-            free: Cell::new(N::U8),
-            head: Cell::new(0),
-            initialized: Cell::new(0),
-            memory: UnsafeCell::new(MaybeUninit::uninitialized()),
+    loom_const_fn! {
+        /// Creates a new memory pool
+        pub fn new() -> Self {
+            Pool {
+                _not_send_or_sync: PhantomData,
+                _observer: PhantomData,
+                free: Cell::new(N::U8),
+                head: Cell::new(0),
+                initialized: Cell::new(0),
+                memory: UnsafeCell::new(MaybeUninit::uninitialized()),
+                reserved: Cell::new(0),
+            }
         }
     }
 }
@@ -179,10 +437,42 @@ where
 mod sealed {
     pub unsafe trait Dealloc {
         unsafe fn dealloc(&self, value: u8);
+
+        fn unreserve(&self, k: u8);
+    }
+}
+
+/// Deserializes a value into a `Box` allocated on the memory pool `P` (see `Box::seed`)
+#[cfg(feature = "serde")]
+pub struct BoxSeed<'a, P>
+where
+    P: Singleton,
+{
+    pool: &'a P,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a, T, N, O, P> serde::de::DeserializeSeed<'de> for BoxSeed<'a, P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: serde::Deserialize<'de>,
+{
+    type Value = Box<P>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Box<P>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+
+        Box::new(self.pool, value).map_err(serde::de::Error::custom)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
+#[allow(clippy::drop_non_drop, clippy::just_underscores_and_digits)]
 mod tests {
     use core::sync::atomic::{AtomicUsize, Ordering};
 
@@ -190,6 +480,7 @@ mod tests {
     use owned_singleton::Singleton;
 
     use super::{Box, Pool};
+    use crate::error::Cause;
 
     #[test]
     fn sanity() {
@@ -224,13 +515,13 @@ mod tests {
         assert_eq!(pool.head.get(), 0);
         assert_eq!(pool.free.get(), 2);
         assert_eq!(pool.initialized.get(), 3);
-        assert_eq!(unsafe { *(pool.memory.get() as *const i8) }, 3);
+        assert_eq!(unsafe { *(pool.slot(0) as *const i8) }, 3);
 
         drop(_2);
         assert_eq!(pool.head.get(), 2);
         assert_eq!(pool.free.get(), 3);
         assert_eq!(pool.initialized.get(), 3);
-        assert_eq!(unsafe { *((pool.memory.get() as *const i8).add(2)) }, 0);
+        assert_eq!(unsafe { *(pool.slot(2) as *const i8) }, 0);
 
         let _2 = Box::new(pool, -4).unwrap();
         assert_eq!(*_2, -4);
@@ -238,13 +529,14 @@ mod tests {
         assert_eq!(pool.head.get(), 0);
         assert_eq!(pool.free.get(), 2);
         assert_eq!(pool.initialized.get(), 4);
-        assert_eq!(unsafe { *((pool.memory.get() as *const i8).add(3)) }, 4);
+        assert_eq!(unsafe { *(pool.slot(3) as *const i8) }, 4);
     }
 
     #[test]
     fn destructor() {
         static COUNT: AtomicUsize = AtomicUsize::new(0);
 
+        #[allow(dead_code)]
         pub struct A(usize);
 
         impl A {
@@ -307,6 +599,69 @@ mod tests {
         assert_eq!(_0.index, 0);
     }
 
+    #[test]
+    fn reserve() {
+        #[Singleton]
+        static mut P: Pool<i8, U4> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        let _0 = Box::new(pool, 0).unwrap();
+        assert_eq!(Pool::reserve(pool, 4).err(), Some(3));
+
+        let mut reservation = Pool::reserve(pool, 2).ok().unwrap();
+        assert_eq!(pool.free.get(), 1);
+
+        // other allocations can't eat into the reservation
+        let _1 = Box::new(pool, 1).unwrap();
+        assert!(Box::new(pool, -1).is_err());
+
+        let _2 = reservation.alloc(2);
+        assert_eq!((*_2, _2.index), (2, 2));
+        assert_eq!(reservation.len(), 1);
+
+        // unused slots go back to the pool
+        drop(reservation);
+        assert_eq!(pool.free.get(), 1);
+
+        let _3 = Box::new(pool, 3).unwrap();
+        assert_eq!(_3.index, 3);
+        assert!(Box::new(pool, -1).is_err());
+
+        let mut reservation = Pool::reserve(pool, 0).ok().unwrap();
+        assert_eq!(
+            reservation.try_alloc(4).err().map(|e| e.cause()),
+            Some(Cause::ReservationMissing)
+        );
+    }
+
+    #[test]
+    fn transfer() {
+        #[Singleton]
+        static mut P: Pool<i32, U1> = Pool::new();
+
+        #[Singleton]
+        static mut Q: Pool<i32, U1> = Pool::new();
+
+        let ref p = unsafe { P::new() };
+        let ref q = unsafe { Q::new() };
+
+        let a = Box::new(p, 0).unwrap();
+        let b = a.try_clone_in(q).unwrap();
+        assert_eq!(a.try_clone_in(q).err().map(|e| e.into_inner()), Some(0));
+
+        // `dst` is full
+        let a = match a.transfer(q) {
+            Err(a) => a,
+            Ok(_) => unreachable!(),
+        };
+
+        drop(b);
+        let a = a.transfer(q).ok().unwrap();
+        assert_eq!(*a, 0);
+        assert_eq!((p.free.get(), q.free.get()), (1, 0));
+    }
+
     #[test]
     fn max_capacity() {
         #[Singleton]
@@ -321,4 +676,28 @@ mod tests {
 
         assert!(Box::new(pool, -1).is_err())
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde::de::DeserializeSeed;
+
+        #[Singleton]
+        static mut P: Pool<[u8; 2], U2> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        let _reservation = Pool::reserve(pool, 1).unwrap();
+        let a = Box::seed(pool)
+            .deserialize(&mut serde_json::Deserializer::from_str("[1,2]"))
+            .unwrap();
+        assert_eq!(serde_json::to_string(&a).unwrap(), "[1,2]");
+
+        // the reserved slot is not counted as live but can't be allocated either
+        let stats = pool.stats();
+        assert_eq!((stats.capacity, stats.live, stats.reserved), (2, 1, 1));
+        assert!(Box::seed(pool)
+            .deserialize(&mut serde_json::Deserializer::from_str("[3,4]"))
+            .is_err());
+    }
 }
@@ -0,0 +1,246 @@
+//! Per-consumer limits on the number of live values allocated on a shared memory pool
+
+use core::{cell::Cell, fmt, ops};
+
+use generic_array::ArrayLength;
+use owned_singleton::Singleton;
+
+use super::{Box, Pool};
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+};
+
+/// A consumer of the memory pool `P` that can hold at most `max` live values at any time
+///
+/// All quotas allocate from the same free list; a quota only limits how much of the pool its
+/// consumer can hold on to.
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{consts::*, pool::unsend::{quota::Quota, Pool}};
+///
+/// #[Singleton]
+/// static P: Pool<[u8; 64], U8> = Pool::new();
+///
+/// let pool = unsafe { P::new() };
+///
+/// let uart = Quota::new(&pool, 2);
+/// let usb = Quota::new(&pool, 4);
+///
+/// let a = uart.alloc([0; 64]).ok().unwrap();
+/// let b = uart.alloc([1; 64]).ok().unwrap();
+///
+/// // UART has reached its quota ...
+/// assert!(uart.alloc([2; 64]).is_err());
+///
+/// // ... but that doesn't affect USB
+/// let c = usb.alloc([3; 64]).ok().unwrap();
+///
+/// let stats = uart.stats();
+/// assert_eq!((stats.live, stats.over_quota), (2, 1));
+/// ```
+pub struct Quota<'p, P>
+where
+    P: Singleton,
+{
+    exhausted: Cell<u32>,
+    live: Cell<u8>,
+    max: u8,
+    over_quota: Cell<u32>,
+    peak: Cell<u8>,
+    pool: &'p P,
+}
+
+/// Usage statistics of a `Quota`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Stats {
+    /// Number of allocations that failed because the pool was exhausted
+    pub exhausted: u32,
+    /// Number of values currently allocated through the quota
+    pub live: u8,
+    /// Maximum number of values the quota can hold
+    pub max: u8,
+    /// Number of allocations that failed because the quota had been reached
+    pub over_quota: u32,
+    /// Highest number of values simultaneously held by the quota
+    pub peak: u8,
+}
+
+impl<'p, T, N, O, P> Quota<'p, P>
+where
+    P: Singleton<Type = Pool<T, N, O>> + ops::Deref<Target = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    /// Creates a quota that can hold at most `max` live values allocated on the `pool`
+    pub fn new(pool: &'p P, max: u8) -> Self {
+        Quota {
+            exhausted: Cell::new(0),
+            live: Cell::new(0),
+            max,
+            over_quota: Cell::new(0),
+            peak: Cell::new(0),
+            pool,
+        }
+    }
+
+    /// Allocates the given `value` on the pool
+    ///
+    /// # Errors
+    ///
+    /// If the quota has been reached, or the memory pool has been exhausted, an error containing
+    /// `value` is returned
+    pub fn alloc(&self, value: T) -> Result<QuotaBox<'_, P>, AllocError<T>> {
+        let live = self.live.get();
+        if live >= self.max {
+            self.over_quota.set(self.over_quota.get().wrapping_add(1));
+            return Err(AllocError::new(value, Cause::QuotaExceeded)
+                .with_usage(usize::from(live), usize::from(self.max)));
+        }
+
+        match Box::new(self.pool, value) {
+            Ok(inner) => {
+                self.live.set(live + 1);
+                if live + 1 > self.peak.get() {
+                    self.peak.set(live + 1);
+                }
+
+                Ok(QuotaBox {
+                    inner,
+                    live: &self.live,
+                })
+            }
+            Err(e) => {
+                self.exhausted.set(self.exhausted.get().wrapping_add(1));
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'p, P> Quota<'p, P>
+where
+    P: Singleton,
+{
+    /// Returns the usage statistics of this quota
+    pub fn stats(&self) -> Stats {
+        Stats {
+            exhausted: self.exhausted.get(),
+            live: self.live.get(),
+            max: self.max,
+            over_quota: self.over_quota.get(),
+            peak: self.peak.get(),
+        }
+    }
+}
+
+/// A value allocated through a `Quota`
+///
+/// - `QuotaBox` destructor returns the memory to the pool and gives the slot back to the quota
+pub struct QuotaBox<'q, P>
+where
+    P: Singleton,
+    P::Type: super::sealed::Dealloc,
+{
+    inner: Box<P>,
+    live: &'q Cell<u8>,
+}
+
+impl<'q, P> Drop for QuotaBox<'q, P>
+where
+    P: Singleton,
+    P::Type: super::sealed::Dealloc,
+{
+    fn drop(&mut self) {
+        self.live.set(self.live.get() - 1);
+    }
+}
+
+impl<'q, T, N, O, P> ops::Deref for QuotaBox<'q, P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<'q, T, N, O, P> ops::DerefMut for QuotaBox<'q, P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<'q, T, N, O, P> fmt::Debug for QuotaBox<'q, P>
+where
+    P: Singleton<Type = Pool<T, N, O>>,
+    N: ArrayLength<T>,
+    O: PoolObserver,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Quota, Stats};
+    use crate::{error::Cause, nightly::pool::unsend::Pool};
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<i8, U4> = Pool::new();
+
+        let ref pool = unsafe { P::new() };
+
+        let a = Quota::new(pool, 3);
+        let b = Quota::new(pool, 2);
+
+        let a0 = a.alloc(0).unwrap();
+        let a1 = a.alloc(1).unwrap();
+        let b0 = b.alloc(2).unwrap();
+        let _b1 = b.alloc(3).unwrap();
+        assert_eq!(
+            b.alloc(4).err().map(|e| e.cause()),
+            Some(Cause::QuotaExceeded)
+        );
+
+        // the quota allows it but the pool is exhausted
+        assert_eq!(a.alloc(5).err().map(|e| e.cause()), Some(Cause::Exhausted));
+
+        drop(b0);
+        let a2 = a.alloc(6).unwrap();
+        assert_eq!((*a0, *a1, *a2), (0, 1, 6));
+        assert_eq!(a.alloc(7).err().map(|e| e.into_inner()), Some(7));
+
+        drop(a1);
+        assert_eq!(
+            a.stats(),
+            Stats {
+                exhausted: 1,
+                live: 2,
+                max: 3,
+                over_quota: 1,
+                peak: 3,
+            }
+        );
+        assert_eq!(b.stats().live, 1);
+    }
+}
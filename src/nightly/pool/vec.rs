@@ -0,0 +1,580 @@
+//! Growable vector whose elements live in chunks allocated on a memory pool
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops, ptr,
+};
+
+use generic_array::{
+    typenum::{consts::U256, IsLess, True},
+    ArrayLength, GenericArray,
+};
+use owned_singleton::Singleton;
+
+use super::{Box, Pool, NIL};
+use crate::{error::AllocError, observer::PoolObserver};
+
+/// A chunk of up to `C` elements of a `Vec`
+///
+/// This is the element type of the memory pool that backs a `Vec`
+pub struct Chunk<T, C>
+where
+    C: ArrayLength<T>,
+{
+    buffer: MaybeUninit<GenericArray<T, C>>,
+    len: u8,
+    next: u8,
+    prev: u8,
+}
+
+impl<T, C> Chunk<T, C>
+where
+    C: ArrayLength<T>,
+{
+    fn as_ptr(&self) -> *const T {
+        self.buffer.as_ptr() as *const T
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.buffer.as_mut_ptr() as *mut T
+    }
+}
+
+/// A vector whose elements are stored in chunks allocated on the memory pool `P`
+///
+/// - Chunks are allocated when the vector grows and returned to the pool when it shrinks
+/// - `Vec` must be explicitly freed (`Vec::free`) or the chunks it holds will be leaked
+///
+/// # Example
+///
+/// ```
+/// use owned_singleton::Singleton;
+/// use alloc_singleton::nightly::{
+///     consts::*,
+///     pool::{vec::{Chunk, Vec}, Pool},
+/// };
+///
+/// #[Singleton]
+/// static mut P: Pool<Chunk<u32, U4>, U2> = Pool::new();
+///
+/// let mut pool = unsafe { P::new() };
+///
+/// let mut xs: Vec<P> = Vec::new();
+///
+/// // 8 elements fit in the two chunks of the pool
+/// assert!(xs.extend(&mut pool, 0..8).is_ok());
+/// assert_eq!(xs.push(&mut pool, 8).err().unwrap().into_inner(), 8);
+///
+/// assert_eq!(xs.pop(&mut pool), Some(7));
+/// assert_eq!(xs.iter().sum::<u32>(), 21);
+///
+/// // return the chunks to the pool or the memory will be leaked
+/// xs.free(&mut pool);
+/// ```
+pub struct Vec<P>
+where
+    P: Singleton,
+{
+    _not_send_or_sync: PhantomData<*const ()>,
+    _pool: PhantomData<P>,
+    head: u8,
+    len: usize,
+    tail: u8,
+}
+
+impl<T, C, N, O, P> Vec<P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>> + ops::DerefMut<Target = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T> + IsLess<U256, Output = True>,
+    N: ArrayLength<Chunk<T, C>>,
+    O: PoolObserver,
+{
+    /// Creates an empty vector; this doesn't allocate
+    pub fn new() -> Self {
+        Vec {
+            _not_send_or_sync: PhantomData,
+            _pool: PhantomData,
+            head: NIL,
+            len: 0,
+            tail: NIL,
+        }
+    }
+
+    /// Appends `value` to the back of the vector
+    ///
+    /// # Errors
+    ///
+    /// If a new chunk is needed and the memory pool has been exhausted an error containing `value`
+    /// is returned
+    pub fn push(&mut self, pool: &mut P, value: T) -> Result<(), AllocError<T>> {
+        unsafe {
+            if self.tail == NIL || (*Pool::slot_of::<P>(self.tail)).len == C::U8 {
+                let index = match Box::new(
+                    pool,
+                    Chunk {
+                        buffer: MaybeUninit::uninitialized(),
+                        len: 0,
+                        next: NIL,
+                        prev: self.tail,
+                    },
+                ) {
+                    Ok(chunk) => chunk.index,
+                    Err(e) => return Err(e.map(|_| value)),
+                };
+
+                if self.tail == NIL {
+                    self.head = index;
+                } else {
+                    (*Pool::slot_of::<P>(self.tail)).next = index;
+                }
+                self.tail = index;
+            }
+
+            let tail = &mut *Pool::slot_of::<P>(self.tail);
+            ptr::write(tail.as_mut_ptr().add(usize::from(tail.len)), value);
+            tail.len += 1;
+            self.len += 1;
+
+            Ok(())
+        }
+    }
+
+    /// Removes the last element of the vector and returns it, or `None` if it's empty
+    ///
+    /// The last chunk is returned to the `pool` once it becomes empty
+    pub fn pop(&mut self, pool: &mut P) -> Option<T> {
+        if self.tail == NIL {
+            return None;
+        }
+
+        unsafe {
+            let tail = &mut *Pool::slot_of::<P>(self.tail);
+            tail.len -= 1;
+            let value = ptr::read(tail.as_ptr().add(usize::from(tail.len)));
+            self.len -= 1;
+
+            if tail.len == 0 {
+                let prev = tail.prev;
+
+                Box::free(handle::<P>(self.tail), pool);
+
+                if prev == NIL {
+                    self.head = NIL;
+                } else {
+                    (*Pool::slot_of::<P>(prev)).next = NIL;
+                }
+                self.tail = prev;
+            }
+
+            Some(value)
+        }
+    }
+
+    /// Appends all the elements yielded by `iter` to the back of the vector
+    ///
+    /// # Errors
+    ///
+    /// If the memory pool gets exhausted an error containing the element that couldn't be
+    /// appended is returned. The elements yielded before that one remain in the vector.
+    pub fn extend<I>(&mut self, pool: &mut P, iter: I) -> Result<(), AllocError<T>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for value in iter {
+            self.push(pool, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes all the elements of the vector and returns its chunks to the `pool`
+    pub fn clear(&mut self, pool: &mut P) {
+        while self.tail != NIL {
+            unsafe {
+                let tail = &mut *Pool::slot_of::<P>(self.tail);
+                let prev = tail.prev;
+
+                for i in 0..usize::from(tail.len) {
+                    ptr::drop_in_place(tail.as_mut_ptr().add(i));
+                }
+
+                Box::free(handle::<P>(self.tail), pool);
+
+                self.tail = prev;
+            }
+        }
+
+        self.head = NIL;
+        self.len = 0;
+    }
+
+    /// Drops all the elements of the vector and returns its chunks to the `pool`
+    pub fn free(mut self, pool: &mut P) {
+        self.clear(pool)
+    }
+
+    /// Returns a reference to the element at position `index` or `None` if out of bounds
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.locate(index).map(|p| unsafe { &*p })
+    }
+
+    /// Returns a mutable reference to the element at position `index` or `None` if out of bounds
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.locate(index).map(|p| unsafe { &mut *p })
+    }
+
+    // Number of elements that can be pushed before the pool is exhausted
+    pub(super) fn spare(&self, pool: &P) -> usize {
+        let tail = if self.tail == NIL {
+            0
+        } else {
+            unsafe { C::USIZE - usize::from((*Pool::slot_of::<P>(self.tail)).len) }
+        };
+
+        tail + usize::from(pool.list.free) * C::USIZE
+    }
+
+    fn locate(&self, mut index: usize) -> Option<*mut T> {
+        if index >= self.len {
+            return None;
+        }
+
+        unsafe {
+            let mut current = self.head;
+            while index >= C::USIZE {
+                current = (*Pool::slot_of::<P>(current)).next;
+                index -= C::USIZE;
+            }
+
+            Some((*Pool::slot_of::<P>(current)).as_mut_ptr().add(index))
+        }
+    }
+}
+
+impl<P> Vec<P>
+where
+    P: Singleton,
+{
+    /// Returns the number of elements in the vector
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the elements of the vector
+    pub fn iter(&self) -> Iter<'_, P> {
+        Iter {
+            _vec: PhantomData,
+            chunk: self.head,
+            pos: 0,
+            len: self.len,
+        }
+    }
+
+    /// Returns an iterator that allows modifying the elements of the vector
+    pub fn iter_mut(&mut self) -> IterMut<'_, P> {
+        IterMut {
+            _vec: PhantomData,
+            chunk: self.head,
+            pos: 0,
+            len: self.len,
+        }
+    }
+}
+
+impl<T, C, N, O, P> Default for Vec<P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>> + ops::DerefMut<Target = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T> + IsLess<U256, Output = True>,
+    N: ArrayLength<Chunk<T, C>>,
+    O: PoolObserver,
+{
+    fn default() -> Self {
+        Vec::new()
+    }
+}
+
+impl<T, C, N, O, P> fmt::Debug for Vec<P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, C, N, O, P> IntoIterator for &'a Vec<P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+    T: 'a,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, P>;
+
+    fn into_iter(self) -> Iter<'a, P> {
+        self.iter()
+    }
+}
+
+impl<'a, T, C, N, O, P> IntoIterator for &'a mut Vec<P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+    T: 'a,
+{
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, P>;
+
+    fn into_iter(self) -> IterMut<'a, P> {
+        self.iter_mut()
+    }
+}
+
+unsafe impl<T, C, N, O, P> Send for Vec<P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+    T: Send,
+{
+}
+
+unsafe impl<T, C, N, O, P> Sync for Vec<P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+    T: Sync,
+{
+}
+
+/// Immutable `Vec` iterator
+pub struct Iter<'a, P>
+where
+    P: Singleton,
+{
+    _vec: PhantomData<&'a Vec<P>>,
+    chunk: u8,
+    len: usize,
+    pos: u8,
+}
+
+impl<'a, T, C, N, O, P> Iterator for Iter<'a, P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+    T: 'a,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        unsafe { next::<_, _, _, _, P>(&mut self.chunk, &mut self.pos, &mut self.len).map(|p| &*p) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+/// Mutable `Vec` iterator
+pub struct IterMut<'a, P>
+where
+    P: Singleton,
+{
+    _vec: PhantomData<&'a mut Vec<P>>,
+    chunk: u8,
+    len: usize,
+    pos: u8,
+}
+
+impl<'a, T, C, N, O, P> Iterator for IterMut<'a, P>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+    T: 'a,
+{
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        unsafe {
+            next::<_, _, _, _, P>(&mut self.chunk, &mut self.pos, &mut self.len).map(|p| &mut *p)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+unsafe fn next<T, C, N, O, P>(current: &mut u8, pos: &mut u8, len: &mut usize) -> Option<*mut T>
+where
+    P: Singleton<Type = Pool<Chunk<T, C>, N, O>>,
+    C: ArrayLength<T>,
+    N: ArrayLength<Chunk<T, C>>,
+{
+    if *len == 0 {
+        return None;
+    }
+
+    let mut slot = &mut *Pool::slot_of::<P>(*current);
+    if *pos == slot.len {
+        *current = slot.next;
+        *pos = 0;
+        slot = &mut *Pool::slot_of::<P>(*current);
+    }
+
+    let p = slot.as_mut_ptr().add(usize::from(*pos));
+    *pos += 1;
+    *len -= 1;
+
+    Some(p)
+}
+
+fn handle<P>(index: u8) -> Box<P>
+where
+    P: Singleton,
+{
+    Box {
+        _not_send_or_sync: PhantomData,
+        _pool: PhantomData,
+        index,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use generic_array::typenum::consts::*;
+    use owned_singleton::Singleton;
+
+    use super::{Chunk, Vec};
+    use crate::{error::Cause, nightly::pool::Pool, observer::PoolObserver};
+
+    #[test]
+    fn sanity() {
+        #[Singleton]
+        static mut P: Pool<Chunk<i32, U3>, U3> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut xs: Vec<P> = Vec::new();
+        assert!(xs.is_empty());
+        assert_eq!(xs.pop(pool), None);
+
+        xs.extend(pool, 0..7).unwrap();
+        assert_eq!(xs.len(), 7);
+        assert_eq!(pool.list.free, 0);
+        assert!(xs.iter().cloned().eq(0..7));
+        assert_eq!(xs.get(6), Some(&6));
+        assert_eq!(xs.get(7), None);
+
+        for x in xs.iter_mut() {
+            *x *= 2;
+        }
+
+        xs.push(pool, 7).unwrap();
+        xs.push(pool, 8).unwrap();
+        let e = xs.push(pool, 9).err().unwrap();
+        assert_eq!(
+            (e.cause(), e.live(), e.into_inner()),
+            (Cause::Exhausted, Some(3), 9)
+        );
+
+        // emptying the last chunk returns it to the pool
+        assert_eq!(xs.pop(pool), Some(8));
+        assert_eq!(xs.pop(pool), Some(7));
+        assert_eq!(xs.pop(pool), Some(12));
+        assert_eq!(pool.list.free, 1);
+
+        let mut ys: Vec<P> = Vec::new();
+        ys.push(pool, -1).unwrap();
+        assert_eq!(pool.list.free, 0);
+
+        Vec::free(xs, pool);
+        assert_eq!(pool.list.free, 2);
+
+        assert_eq!(ys.pop(pool), Some(-1));
+        assert_eq!(ys.pop(pool), None);
+        assert_eq!(pool.list.free, 3);
+    }
+
+    #[test]
+    fn destructor() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        pub struct A;
+
+        impl A {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Chunk<A, U2>, U4> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut xs: Vec<P> = Vec::new();
+        for _ in 0..5 {
+            xs.push(pool, A::new()).ok().unwrap();
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 5);
+
+        // Freeing the `Vec` should drop all its elements
+        Vec::free(xs, pool);
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.list.free, 4);
+    }
+
+    #[test]
+    fn observer() {
+        static CHUNKS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counter;
+
+        impl PoolObserver for Counter {
+            fn on_alloc(_: u8) {
+                CHUNKS.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn on_free(_: u8) {
+                CHUNKS.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut P: Pool<Chunk<u8, U2>, U2, Counter> = Pool::new();
+
+        let ref mut pool = unsafe { P::new() };
+
+        let mut xs: Vec<P> = Vec::new();
+        xs.extend(pool, 0..3).unwrap();
+        assert_eq!(CHUNKS.load(Ordering::SeqCst), 2);
+
+        xs.free(pool);
+        assert_eq!(CHUNKS.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,95 @@
+//! Allocation tracking hooks
+//!
+//! Every memory pool takes an observer type parameter that defaults to `()`. The observer's hooks
+//! are associated functions, not methods, so they are resolved at compile time: the default
+//! observer compiles down to nothing.
+//!
+//! # Example
+//!
+//! ```
+//! use core::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! use alloc_singleton::{observer::PoolObserver, stable::pool::Pool};
+//! use owned_singleton::Singleton;
+//!
+//! static EXHAUSTED: AtomicUsize = AtomicUsize::new(0);
+//!
+//! struct Alarm;
+//!
+//! impl PoolObserver for Alarm {
+//!     fn on_exhausted() {
+//!         EXHAUSTED.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! #[Singleton]
+//! static mut M: [i32; 1] = [0; 1];
+//!
+//! let mut pool: Pool<M, Alarm> = Pool::with_observer(unsafe { M::new() });
+//!
+//! let _x = pool.alloc(1).unwrap();
+//! assert!(pool.alloc(2).is_err());
+//! assert_eq!(EXHAUSTED.load(Ordering::Relaxed), 1);
+//! ```
+
+/// Hooks invoked by a memory pool when it hands out and takes back slots
+///
+/// All the hooks do nothing by default.
+///
+/// *NOTE*: The hooks run in the context that performs the operation. In particular, the
+/// `multicore` pool calls `on_free` on the core that drops the `Box`.
+pub trait PoolObserver {
+    /// Called when the slot `index` is handed out to hold a new value
+    fn on_alloc(_index: u8) {}
+
+    /// Called when the slot `index` is returned to the pool
+    fn on_free(_index: u8) {}
+
+    /// Called when an allocation fails because the pool has no free slot left
+    fn on_exhausted() {}
+}
+
+/// The observer that does nothing
+impl PoolObserver for () {}
+
+/// An observer that logs every hook through the `log` crate
+///
+/// The messages are logged with `trace!`, except for exhaustion which is logged with `warn!`.
+#[cfg(feature = "log")]
+pub struct Log;
+
+#[cfg(feature = "log")]
+impl PoolObserver for Log {
+    fn on_alloc(index: u8) {
+        log::trace!("alloc: slot {}", index);
+    }
+
+    fn on_free(index: u8) {
+        log::trace!("free: slot {}", index);
+    }
+
+    fn on_exhausted() {
+        log::warn!("memory pool exhausted");
+    }
+}
+
+/// An observer that logs every hook through the `defmt` crate
+///
+/// The messages are logged with `trace!`, except for exhaustion which is logged with `warn!`.
+#[cfg(feature = "defmt")]
+pub struct Defmt;
+
+#[cfg(feature = "defmt")]
+impl PoolObserver for Defmt {
+    fn on_alloc(index: u8) {
+        defmt::trace!("alloc: slot {=u8}", index);
+    }
+
+    fn on_free(index: u8) {
+        defmt::trace!("free: slot {=u8}", index);
+    }
+
+    fn on_exhausted() {
+        defmt::warn!("memory pool exhausted");
+    }
+}
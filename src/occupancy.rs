@@ -0,0 +1,58 @@
+//! Live slots of a memory pool
+
+/// Iterator over the indices of the slots of a memory pool that hold a live value
+///
+/// The pools don't track liveness; this is computed on demand by walking the free list
+pub(crate) struct Occupancy {
+    // one bit per slot; set: live
+    bits: [u32; 8],
+    index: u8,
+    initialized: u8,
+}
+
+impl Occupancy {
+    /// `link(index)` must return the index of the next free slot stored in the free slot `index`
+    pub(crate) unsafe fn new<F>(head: u8, free: u8, initialized: u8, link: F) -> Self
+    where
+        F: Fn(u8) -> u8,
+    {
+        let mut bits = [0; 8];
+        for index in 0..initialized {
+            bits[usize::from(index / 32)] |= 1 << (index % 32);
+        }
+
+        // NOTE the free list ends with the slots that have not yet been initialized
+        let mut index = head;
+        for _ in 0..free {
+            if index >= initialized {
+                break;
+            }
+
+            bits[usize::from(index / 32)] &= !(1 << (index % 32));
+            index = link(index);
+        }
+
+        Occupancy {
+            bits,
+            index: 0,
+            initialized,
+        }
+    }
+}
+
+impl Iterator for Occupancy {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.index < self.initialized {
+            let index = self.index;
+            self.index += 1;
+
+            if self.bits[usize::from(index / 32)] & (1 << (index % 32)) != 0 {
+                return Some(index);
+            }
+        }
+
+        None
+    }
+}
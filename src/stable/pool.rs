@@ -1,16 +1,34 @@
 //! Fixed size memory pool
 
-use core::{fmt, marker::PhantomData, mem, ops, ptr, u8};
+use core::{
+    cmp, fmt,
+    future::Future,
+    hash,
+    marker::PhantomData,
+    mem, ops,
+    pin::Pin,
+    ptr,
+    task::{Context, Poll},
+};
 
 use as_slice::{AsMutSlice, AsSlice};
 use owned_singleton::Singleton;
 use stable_deref_trait::StableDeref;
 
+use crate::{
+    error::{AllocError, Cause},
+    observer::PoolObserver,
+    occupancy::Occupancy,
+    stats::PoolStats,
+};
+
 /// A value allocated on the memory pool `Pool<M>`
 ///
 /// - `sizeof(Box<_>)` is a single byte
 /// - `Box<M>` implements `Send` if it derefs to a type `T` that implements `Send`
 /// - `Box<M>` implements `Sync` if it derefs to a type `T` that implements `Sync`
+/// - `Box<M>` can be handed to DMA APIs (`embedded_dma::{ReadBuffer, WriteBuffer}`) if it derefs to
+///   a word buffer like `[u16; N]` or `Aligned<A, [u8; N]>` (requires the `embedded-dma` feature)
 pub struct Box<M>
 where
     M: Singleton,
@@ -50,6 +68,13 @@ where
     }
 }
 
+// NOTE moving the `Box` doesn't move the value, which stays in its slot
+impl<M> Unpin for Box<M>
+where
+    M: Singleton,
+{
+}
+
 unsafe impl<T, M> StableDeref for Box<M>
 where
     M: Singleton,
@@ -79,6 +104,179 @@ where
     }
 }
 
+impl<T, M> fmt::Pointer for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ptr: *const T = &**self;
+        fmt::Pointer::fmt(&ptr, f)
+    }
+}
+
+impl<T, M> PartialEq for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        T::eq(&**self, &**other)
+    }
+}
+
+impl<T, M> Eq for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+    T: Eq,
+{
+}
+
+impl<T, M> PartialOrd for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+    T: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        T::partial_cmp(&**self, &**other)
+    }
+}
+
+impl<T, M> Ord for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+    T: Ord,
+{
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        T::cmp(&**self, &**other)
+    }
+}
+
+impl<T, M> hash::Hash for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+    T: hash::Hash,
+{
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: hash::Hasher,
+    {
+        T::hash(&**self, state)
+    }
+}
+
+impl<T, M> AsRef<T> for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+{
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T, M> AsMut<T> for Box<M>
+where
+    M: Singleton,
+    M::Type: AsMutSlice<Element = T>,
+{
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T, M> Iterator for Box<M>
+where
+    M: Singleton,
+    M::Type: AsMutSlice<Element = T>,
+    T: Iterator,
+{
+    type Item = T::Item;
+
+    fn next(&mut self) -> Option<T::Item> {
+        T::next(&mut **self)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        T::size_hint(&**self)
+    }
+}
+
+impl<T, M> DoubleEndedIterator for Box<M>
+where
+    M: Singleton,
+    M::Type: AsMutSlice<Element = T>,
+    T: DoubleEndedIterator,
+{
+    fn next_back(&mut self) -> Option<T::Item> {
+        T::next_back(&mut **self)
+    }
+}
+
+impl<T, M> ExactSizeIterator for Box<M>
+where
+    M: Singleton,
+    M::Type: AsMutSlice<Element = T>,
+    T: ExactSizeIterator,
+{
+}
+
+impl<T, M> Future for Box<M>
+where
+    M: Singleton,
+    M::Type: AsMutSlice<Element = T>,
+    T: Future + Unpin,
+{
+    type Output = T::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<T::Output> {
+        T::poll(Pin::new(&mut **self), cx)
+    }
+}
+
+impl<T, M> fmt::Write for Box<M>
+where
+    M: Singleton,
+    M::Type: AsMutSlice<Element = T>,
+    T: fmt::Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        T::write_str(&mut **self, s)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<T, M> defmt::Format for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+    T: defmt::Format,
+{
+    fn format(&self, f: defmt::Formatter) {
+        T::format(self, f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, M> serde::Serialize for Box<M>
+where
+    M: Singleton,
+    M::Type: AsSlice<Element = T>,
+    T: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        T::serialize(self, serializer)
+    }
+}
+
 unsafe impl<T, M> Send for Box<M>
 where
     M: Singleton,
@@ -95,8 +293,90 @@ where
 {
 }
 
+/// Slots of the memory pool `Pool<M>` set aside for later, infallible, allocations (see
+/// `Pool::reserve`)
+///
+/// - `Reservation` borrows the pool; the pool can still be used through `Reservation::pool`
+/// - `Reservation` destructor returns the slots that were not used to the pool
+pub struct Reservation<'p, M, O = ()>
+where
+    M: Singleton,
+{
+    len: u8,
+    pool: &'p mut Pool<M, O>,
+}
+
+impl<'p, T, A, M, O> Reservation<'p, M, O>
+where
+    M: Singleton<Type = A> + ops::DerefMut<Target = A>,
+    A: AsMutSlice<Element = T>,
+    O: PoolObserver,
+{
+    /// Allocates the given `value` on one of the reserved slots
+    ///
+    /// # Panics
+    ///
+    /// This method panics if all the reserved slots have already been used (see `try_alloc`)
+    pub fn alloc(&mut self, value: T) -> Box<M> {
+        assert!(self.len != 0, "all the reserved slots have been used");
+
+        self.len -= 1;
+        self.pool.reserved -= 1;
+
+        unsafe { self.pool.take(value) }
+    }
+
+    /// Allocates the given `value` on one of the reserved slots
+    ///
+    /// # Errors
+    ///
+    /// If all the reserved slots have already been used an error containing `value` is returned
+    pub fn try_alloc(&mut self, value: T) -> Result<Box<M>, AllocError<T>> {
+        if self.len == 0 {
+            Err(AllocError::new(value, Cause::ReservationMissing))
+        } else {
+            Ok(self.alloc(value))
+        }
+    }
+}
+
+impl<'p, M, O> Reservation<'p, M, O>
+where
+    M: Singleton,
+{
+    /// Returns the pool the slots were reserved on
+    ///
+    /// The pool can be used as usual but it can't allocate on the slots that are still reserved
+    pub fn pool(&mut self) -> &mut Pool<M, O> {
+        self.pool
+    }
+
+    /// Returns the number of reserved slots that have not been used yet
+    pub fn len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns `true` if all the reserved slots have been used
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'p, M, O> Drop for Reservation<'p, M, O>
+where
+    M: Singleton,
+{
+    fn drop(&mut self) {
+        self.pool.free += self.len;
+        self.pool.reserved -= self.len;
+    }
+}
+
 /// A fixed-size memory pool backed by the memory chunk behind the owned singleton `M`
 ///
+/// Slots are aligned to the alignment of `M::Type::Element`; use `align::Aligned` as the element
+/// type to over-align them (e.g. for DMA buffers)
+///
 /// # Example
 ///
 /// ```
@@ -115,14 +395,18 @@ where
 /// // return the memory to the pool or the memory will be leaked
 /// pool.dealloc(buffer);
 /// ```
-pub struct Pool<M>
+pub struct Pool<M, O = ()>
 where
     M: Singleton,
 {
+    _observer: PhantomData<O>,
+    // free slots that are not reserved
     free: u8,
     head: u8,
     initialized: u8,
     memory: M,
+    // free slots set aside by `Reservation`s; like the other free slots they stay on the free list
+    reserved: u8,
 }
 
 impl<T, A, M> Pool<M>
@@ -142,13 +426,30 @@ where
     ///
     /// This constructor panics if `sizeof(M::Type::Element)` is a zero. In other words, `Pool`
     /// doesn't support ZST.
-    #[allow(unused_variables)]
     pub fn new(memory: M) -> Self {
+        Pool::with_observer(memory)
+    }
+}
+
+impl<T, A, M, O> Pool<M, O>
+where
+    M: Singleton<Type = A> + ops::DerefMut<Target = A>,
+    A: AsMutSlice<Element = T>,
+    O: PoolObserver,
+{
+    /// Creates a memory pool that allocates on the given `memory` chunk and reports its activity to
+    /// the observer `O`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if `sizeof(M::Type::Element)` is a zero
+    pub fn with_observer(memory: M) -> Self {
         assert!(mem::size_of::<T>() > 0);
 
         let capacity = memory.as_slice().len();
 
         Pool {
+            _observer: PhantomData,
             free: if capacity > usize::from(u8::MAX) {
                 u8::MAX
             } else {
@@ -157,6 +458,7 @@ where
             head: 0,
             initialized: 0,
             memory,
+            reserved: 0,
         }
     }
 
@@ -165,47 +467,77 @@ where
     /// # Errors
     ///
     /// If the memory pool has been exhausted an error containing `value` is returned
-    pub fn alloc(&mut self, value: T) -> Result<Box<M>, T> {
-        unsafe {
-            let n = self.memory.as_slice().len() as u8;
+    pub fn alloc(&mut self, value: T) -> Result<Box<M>, AllocError<T>> {
+        if self.free != 0 {
+            self.free -= 1;
+
+            Ok(unsafe { self.take(value) })
+        } else {
+            O::on_exhausted();
 
-            if self.initialized < n {
-                let index = self.initialized;
+            let capacity = cmp::min(self.memory.as_slice().len(), usize::from(u8::MAX));
 
-                let p: *mut T = self
-                    .memory
-                    .as_mut_slice()
-                    .get_unchecked_mut(usize::from(index));
+            Err(AllocError::exhausted(value, capacity, capacity))
+        }
+    }
 
-                // the memory (`M`) starts initialized; we have to deinitialize it before we
-                // overwrite its contents
-                ptr::drop_in_place(p);
+    /// Reserves `k` slots of the pool for the exclusive use of the returned `Reservation`
+    ///
+    /// The reserved slots are removed from the pool's free count so `alloc` can't take them. Values
+    /// are allocated on the reserved slots with `Reservation::alloc`; the slots that are not used
+    /// return to the pool when the `Reservation` is dropped.
+    ///
+    /// # Errors
+    ///
+    /// If the pool has less than `k` free slots nothing is reserved and an error containing the
+    /// number of free slots is returned
+    pub fn reserve(&mut self, k: u8) -> Result<Reservation<'_, M, O>, u8> {
+        if k > self.free {
+            return Err(self.free);
+        }
 
-                *(p as *mut u8) = index + 1;
-                self.initialized += 1;
-            }
+        self.free -= k;
+        self.reserved += k;
 
-            if self.free != 0 {
-                let index = self.head;
-                let p = self
-                    .memory
-                    .as_mut_slice()
-                    .as_mut_ptr()
-                    .add(usize::from(index));
-                self.head = *(p as *const u8);
+        Ok(Reservation { len: k, pool: self })
+    }
 
-                self.free -= 1;
+    // Moves `value` into a slot taken off the free list; the caller must have already accounted
+    // for the slot in `free` or `reserved`
+    unsafe fn take(&mut self, value: T) -> Box<M> {
+        let n = self.memory.as_slice().len() as u8;
 
-                ptr::write(p, value);
+        if self.initialized < n {
+            let index = self.initialized;
 
-                Ok(Box {
-                    _memory: PhantomData,
-                    _not_send_or_sync: PhantomData,
-                    index,
-                })
-            } else {
-                Err(value)
-            }
+            let p: *mut T = self
+                .memory
+                .as_mut_slice()
+                .get_unchecked_mut(usize::from(index));
+
+            // the memory (`M`) starts initialized; we have to deinitialize it before we
+            // overwrite its contents
+            ptr::drop_in_place(p);
+
+            *(p as *mut u8) = index + 1;
+            self.initialized += 1;
+        }
+
+        let index = self.head;
+        let p = self
+            .memory
+            .as_mut_slice()
+            .as_mut_ptr()
+            .add(usize::from(index));
+        self.head = *(p as *const u8);
+
+        ptr::write(p, value);
+        O::on_alloc(index);
+
+        Box {
+            _memory: PhantomData,
+            _not_send_or_sync: PhantomData,
+            index,
         }
     }
 
@@ -226,16 +558,165 @@ where
             self.free += 1;
             self.head = value.index;
         }
+
+        O::on_free(value.index);
+    }
+
+    /// Returns a snapshot of the usage of the pool
+    pub fn stats(&self) -> PoolStats {
+        let capacity = cmp::min(self.memory.as_slice().len(), usize::from(u8::MAX)) as u8;
+
+        PoolStats {
+            capacity,
+            live: capacity - self.free - self.reserved,
+            reserved: self.reserved,
+        }
+    }
+
+    /// Returns a `serde` seed that deserializes a value straight into this pool
+    ///
+    /// Deserialization fails if the pool has been exhausted
+    #[cfg(feature = "serde")]
+    pub fn seed(&mut self) -> BoxSeed<'_, M, O> {
+        BoxSeed { pool: self }
+    }
+
+    /// Returns an iterator over the values currently allocated on the pool and the indices of
+    /// their slots
+    ///
+    /// # Safety
+    ///
+    /// The values must not be mutated (e.g. through their `Box`es) while the iterator is alive
+    pub unsafe fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            _pool: PhantomData,
+            live: self.occupancy(),
+            memory: self.memory.as_slice().as_ptr(),
+        }
+    }
+
+    /// Returns an iterator that allows modifying the values currently allocated on the pool
+    ///
+    /// # Safety
+    ///
+    /// The values must not be accessed through their `Box`es while the iterator is alive
+    pub unsafe fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            _pool: PhantomData,
+            live: self.occupancy(),
+            memory: self.memory.as_mut_slice().as_mut_ptr(),
+        }
+    }
+
+    /// Drops all the values currently allocated on the pool and returns their slots to the pool
+    ///
+    /// Reserved slots stay reserved
+    ///
+    /// # Safety
+    ///
+    /// All the `Box`es allocated on the pool become dangling; they must not be used after this call
+    pub unsafe fn drain(&mut self) {
+        let live = self.occupancy();
+        let p = self.memory.as_mut_slice().as_mut_ptr();
+
+        for index in live {
+            ptr::drop_in_place(p.add(usize::from(index)));
+            self.free += 1;
+            O::on_free(index);
+        }
+
+        for index in 0..self.initialized {
+            *(p.add(usize::from(index)) as *mut u8) = index + 1;
+        }
+
+        self.head = 0;
+    }
+
+    fn occupancy(&self) -> Occupancy {
+        let p = self.memory.as_slice().as_ptr();
+        // NOTE the reserved slots are on the free list too
+        let free = self.free + self.reserved;
+
+        unsafe {
+            Occupancy::new(self.head, free, self.initialized, |index| {
+                *(p.add(usize::from(index)) as *const u8)
+            })
+        }
+    }
+}
+
+/// Iterator over the values allocated on a `Pool`
+pub struct Iter<'a, T> {
+    _pool: PhantomData<&'a T>,
+    live: Occupancy,
+    memory: *const T,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (u8, &'a T);
+
+    fn next(&mut self) -> Option<(u8, &'a T)> {
+        self.live
+            .next()
+            .map(|index| (index, unsafe { &*self.memory.add(usize::from(index)) }))
+    }
+}
+
+/// Mutable iterator over the values allocated on a `Pool`
+pub struct IterMut<'a, T> {
+    _pool: PhantomData<&'a mut T>,
+    live: Occupancy,
+    memory: *mut T,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (u8, &'a mut T);
+
+    fn next(&mut self) -> Option<(u8, &'a mut T)> {
+        self.live
+            .next()
+            .map(|index| (index, unsafe { &mut *self.memory.add(usize::from(index)) }))
+    }
+}
+
+/// Deserializes a value into a `Box` allocated on a `Pool` (see `Pool::seed`)
+#[cfg(feature = "serde")]
+pub struct BoxSeed<'a, M, O>
+where
+    M: Singleton,
+{
+    pool: &'a mut Pool<M, O>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a, T, A, M, O> serde::de::DeserializeSeed<'de> for BoxSeed<'a, M, O>
+where
+    M: Singleton<Type = A> + ops::DerefMut<Target = A>,
+    A: AsMutSlice<Element = T>,
+    O: PoolObserver,
+    T: serde::Deserialize<'de>,
+{
+    type Value = Box<M>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Box<M>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+
+        self.pool.alloc(value).map_err(serde::de::Error::custom)
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::drop_non_drop, clippy::just_underscores_and_digits)]
 mod tests {
     use core::sync::atomic::{AtomicUsize, Ordering};
 
     use owned_singleton::Singleton;
 
     use super::Pool;
+    use crate::error::Cause;
 
     #[test]
     fn sanity() {
@@ -291,6 +772,7 @@ mod tests {
     fn destructor() {
         static COUNT: AtomicUsize = AtomicUsize::new(1);
 
+        #[allow(dead_code)]
         pub struct A(u32);
 
         impl A {
@@ -328,6 +810,7 @@ mod tests {
     fn leak() {
         static COUNT: AtomicUsize = AtomicUsize::new(1);
 
+        #[allow(dead_code)]
         pub struct A(u32);
 
         impl A {
@@ -396,6 +879,110 @@ mod tests {
         assert_eq!(_0.index, 0);
     }
 
+    #[test]
+    fn reserve() {
+        #[Singleton]
+        static mut M: [i8; 4] = [0; 4];
+
+        let mut pool = Pool::new(unsafe { M::new() });
+
+        let _0 = pool.alloc(0).unwrap();
+        assert_eq!(pool.reserve(4).err(), Some(3));
+
+        let mut reservation = pool.reserve(2).ok().unwrap();
+        assert_eq!(reservation.pool().free, 1);
+
+        // other allocations can't eat into the reservation
+        let _1 = reservation.pool().alloc(1).unwrap();
+        assert!(reservation.pool().alloc(-1).is_err());
+
+        let _2 = reservation.alloc(2);
+        assert_eq!((*_2, _2.index), (2, 2));
+        assert_eq!(reservation.len(), 1);
+
+        // unused slots go back to the pool
+        drop(reservation);
+        assert_eq!(pool.free, 1);
+
+        let _3 = pool.alloc(3).unwrap();
+        assert_eq!(_3.index, 3);
+        assert!(pool.alloc(-1).is_err());
+
+        let mut reservation = pool.reserve(0).ok().unwrap();
+        assert_eq!(
+            reservation.try_alloc(4).err().map(|e| e.cause()),
+            Some(Cause::ReservationMissing)
+        );
+    }
+
+    // reserved slots are neither live nor free
+    #[test]
+    fn reserve_iter() {
+        static COUNT: AtomicUsize = AtomicUsize::new(4);
+
+        pub struct A(i32);
+
+        impl A {
+            fn new(x: i32) -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A(x)
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut M: [A; 4] = [A(-1), A(-1), A(-1), A(-1)];
+
+        let mut pool = Pool::new(unsafe { M::new() });
+
+        for i in 0..4 {
+            let x = pool.alloc(A::new(i)).ok().unwrap();
+            pool.dealloc(x);
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+
+        let mut reservation = pool.reserve(2).ok().unwrap();
+        assert_eq!(unsafe { reservation.pool().iter().count() }, 0);
+
+        let stats = reservation.pool().stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (0, 2, 2));
+
+        let _0 = reservation.alloc(A::new(10));
+        let _1 = reservation.pool().alloc(A::new(20)).ok().unwrap();
+        assert_eq!(
+            unsafe { reservation.pool().iter() }
+                .map(|(_, a)| a.0)
+                .collect::<Vec<_>>(),
+            [10, 20]
+        );
+
+        let stats = reservation.pool().stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (2, 1, 1));
+
+        // only the live values are dropped; the slot left in the reservation stays reserved
+        unsafe { reservation.pool().drain() }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 0);
+
+        let stats = reservation.pool().stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (0, 1, 3));
+
+        for i in 0..3 {
+            reservation.pool().alloc(A::new(i)).ok().unwrap();
+        }
+        assert!(reservation.pool().alloc(A::new(3)).is_err());
+        let _3 = reservation.alloc(A::new(3));
+
+        drop(reservation);
+        let stats = pool.stats();
+        assert_eq!((stats.live, stats.reserved, stats.free()), (4, 0, 0));
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+    }
+
     #[test]
     fn max_capacity() {
         #[Singleton]
@@ -409,4 +996,151 @@ mod tests {
 
         assert!(pool.alloc(-1).is_err());
     }
+
+    #[cfg(feature = "embedded-dma")]
+    #[test]
+    fn dma() {
+        use embedded_dma::{ReadBuffer, WriteBuffer};
+
+        use crate::align::{Aligned, A32};
+
+        #[Singleton]
+        static mut M: [Aligned<A32, [u16; 5]>; 2] = [Aligned::new([0; 5]), Aligned::new([0; 5])];
+
+        let mut pool = Pool::new(unsafe { M::new() });
+
+        let mut buffer = pool.alloc(Aligned::new([1; 5])).unwrap();
+
+        let (p, len) = unsafe { buffer.read_buffer() };
+        assert_eq!(p as usize % 32, 0);
+        assert_eq!(len, 5);
+
+        let (p, len) = unsafe { buffer.write_buffer() };
+        unsafe { *p.add(4) = 2 }
+        assert_eq!(len, 5);
+        assert_eq!(**buffer, [1, 1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn iter() {
+        static COUNT: AtomicUsize = AtomicUsize::new(4);
+
+        pub struct A(i32);
+
+        impl A {
+            fn new(x: i32) -> Self {
+                COUNT.fetch_add(1, Ordering::SeqCst);
+                A(x)
+            }
+        }
+
+        impl Drop for A {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        #[Singleton]
+        static mut M: [A; 4] = [A(-1), A(-1), A(-1), A(-1)];
+
+        let mut pool = Pool::new(unsafe { M::new() });
+
+        let _0 = pool.alloc(A::new(0)).ok().unwrap();
+        let _1 = pool.alloc(A::new(1)).ok().unwrap();
+        let _2 = pool.alloc(A::new(2)).ok().unwrap();
+        pool.dealloc(_1);
+        assert_eq!(COUNT.load(Ordering::SeqCst), 3);
+
+        unsafe {
+            for (_, a) in pool.iter_mut() {
+                a.0 += 10;
+            }
+
+            assert_eq!(
+                pool.iter().map(|(i, a)| (i, a.0)).collect::<Vec<_>>(),
+                [(0, 10), (2, 12)]
+            );
+        }
+
+        // only the allocated values are dropped; the never used slot 3 still holds `A(-1)`
+        unsafe { pool.drain() }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 1);
+        assert_eq!(unsafe { pool.iter().count() }, 0);
+        assert_eq!(pool.free, 4);
+
+        for i in 0..4 {
+            let x = pool.alloc(A::new(i)).ok().unwrap();
+            assert_eq!(x.index, i as u8);
+        }
+        assert_eq!(COUNT.load(Ordering::SeqCst), 4);
+        assert!(pool.alloc(A::new(4)).is_err());
+    }
+
+    #[test]
+    fn traits() {
+        use core::fmt::Write;
+        use std::collections::BTreeSet;
+
+        #[Singleton]
+        static mut M: [&'static str; 4] = [""; 4];
+
+        #[Singleton]
+        static mut N: [core::ops::Range<u8>; 2] = [0..0, 0..0];
+
+        #[Singleton]
+        static mut S: [std::string::String; 1] = [std::string::String::new()];
+
+        let mut pool = Pool::new(unsafe { M::new() });
+
+        let a = pool.alloc("a").ok().unwrap();
+        let b = pool.alloc("b").ok().unwrap();
+        let c = pool.alloc("a").ok().unwrap();
+        assert!(a == c && a != b && a < b);
+        assert_eq!(a.as_ref(), &"a");
+        assert_ne!(format!("{:p}", a), format!("{:p}", c));
+
+        let set = vec![a, b, c].into_iter().collect::<BTreeSet<_>>();
+        assert_eq!(set.len(), 2);
+
+        let mut pool = Pool::new(unsafe { N::new() });
+        let range = pool.alloc(0..4).ok().unwrap();
+        assert_eq!(range.rev().collect::<Vec<_>>(), [3, 2, 1, 0]);
+
+        let mut pool = Pool::new(unsafe { S::new() });
+        let mut s = pool.alloc(std::string::String::new()).ok().unwrap();
+        write!(s, "{}-{}", 1, 2).unwrap();
+        assert_eq!(*s, "1-2");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde::de::DeserializeSeed;
+
+        #[Singleton]
+        static mut M: [u32; 1] = [0; 1];
+
+        let mut pool = Pool::new(unsafe { M::new() });
+
+        let x = pool
+            .seed()
+            .deserialize(&mut serde_json::Deserializer::from_str("42"))
+            .unwrap();
+        assert_eq!(*x, 42);
+        assert_eq!(serde_json::to_string(&x).unwrap(), "42");
+
+        let stats = pool.stats();
+        assert_eq!((stats.live, stats.free()), (1, 0));
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"capacity":1,"live":1,"reserved":0}"#
+        );
+
+        let e = pool
+            .seed()
+            .deserialize(&mut serde_json::Deserializer::from_str("0"))
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "memory pool exhausted (1/1 in use)");
+    }
 }
@@ -0,0 +1,21 @@
+//! Memory pool statistics
+
+/// A snapshot of the usage of a memory pool
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct PoolStats {
+    /// Number of slots of the pool
+    pub capacity: u8,
+    /// Number of slots that currently hold a value
+    pub live: u8,
+    /// Number of free slots set aside by reservations; only their reservation can allocate on them
+    pub reserved: u8,
+}
+
+impl PoolStats {
+    /// Returns the number of slots that can still be allocated outside of reservations
+    pub fn free(&self) -> u8 {
+        self.capacity - self.live - self.reserved
+    }
+}
@@ -0,0 +1,47 @@
+//! Synchronization primitives used by the allocators that can be accessed from several contexts
+//!
+//! Under `cfg(loom)` these are replaced by their `loom` counterparts so that the concurrent code can
+//! be model checked.
+
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+#[cfg(loom)]
+pub(crate) use loom::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+/// `core::cell::UnsafeCell` with the closure based API of `loom::cell::UnsafeCell`
+#[cfg(not(loom))]
+#[repr(transparent)]
+pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        UnsafeCell(core::cell::UnsafeCell::new(value))
+    }
+
+    pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        f(self.0.get())
+    }
+
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
+
+// Defines a constructor that's a `const fn` except under `cfg(loom)`, where the `loom` primitives
+// can't be created in a const context
+macro_rules! loom_const_fn {
+    ($(#[$attr:meta])* pub fn $name:ident() -> $ret:ty $body:block) => {
+        $(#[$attr])*
+        #[cfg(not(loom))]
+        pub const fn $name() -> $ret $body
+
+        $(#[$attr])*
+        #[cfg(loom)]
+        pub fn $name() -> $ret $body
+    };
+}